@@ -1,6 +1,14 @@
 from __future__ import annotations
 
-from typing import TypedDict
+import os
+from collections.abc import Callable, Iterator, Mapping, Sequence
+from typing import Any, Literal, Protocol, TypedDict
+
+class SupportsRead(Protocol):
+    def read(self, size: int, /) -> bytes | str: ...
+
+class SelectorError(ValueError): ...
+class ParseError(ValueError): ...
 
 class ElementDict(TypedDict):
     tag: str
@@ -8,26 +16,117 @@ class ElementDict(TypedDict):
     html: str
     attrs: dict[str, str]
 
+class FormFieldDict(TypedDict):
+    name: str | None
+    type: str
+    value: str | None
+    checked: bool
+    options: list[str]
+
+class FormDict(TypedDict):
+    action: str | None
+    method: str
+    fields: list[FormFieldDict]
+
+class DiffDict(TypedDict):
+    op: Literal["added", "removed", "changed"]
+    old: str | None
+    new: str | None
+
 class Element:
     tag: str
     text: str
     html: str
+    outer_html: str
     attrs: dict[str, str]
+    id: str | None
 
     def __repr__(self) -> str: ...
     def attr(self, name: str) -> str | None: ...
     def get(self, name: str, default: str | None = ...) -> str | None: ...
-    def select(self, css: str) -> list[Element]: ...
-    def select_first(self, css: str) -> Element | None: ...
-    def find(self, css: str) -> Element | None: ...
-    def css(self, css: str) -> list[Element]: ...
+    def abs_attr(self, name: str) -> str | None: ...
+    def __getitem__(self, name: str) -> str: ...
+    def __contains__(self, name: object) -> bool: ...
+    def __iter__(self) -> Iterator[str]: ...
+    def keys(self) -> list[str]: ...
+    def values(self) -> list[str]: ...
+    def items(self) -> list[tuple[str, str]]: ...
+    def get_text(
+        self,
+        separator: str = " ",
+        strip: bool = False,
+        *,
+        include_scripts: bool = False,
+        normalize: bool = False,
+        form: Literal["NFC", "NFD", "NFKC", "NFKD"] | None = None,
+    ) -> str: ...
+    def to_text(self) -> str: ...
+    def to_markdown(self) -> str: ...
+    def classes(self) -> list[str]: ...
+    def has_class(self, name: str) -> bool: ...
+    def parent(self) -> Element | None: ...
+    def closest(self, css: str) -> Element | None: ...
+    def matches(self, css: str) -> bool: ...
+    def find_parent(self, css: str) -> Element | None: ...
+    def find_parents(self, css: str) -> list[Element]: ...
+    def find_next(self, css: str) -> Element | None: ...
+    def find_previous(self, css: str) -> Element | None: ...
+    def lang(self) -> str | None: ...
+    def depth(self) -> int | None: ...
+    def sibling_index(self) -> int | None: ...
+    def css_path(self) -> str | None: ...
+    def children(self) -> list[Element]: ...
+    def descendants(self) -> list[Element]: ...
+    def next_sibling(self) -> Element | None: ...
+    def previous_sibling(self) -> Element | None: ...
+    def remove(self) -> None: ...
+    def set_attr(self, name: str, value: str) -> None: ...
+    def remove_attr(self, name: str) -> None: ...
+    def select(self, css: str, *, include_scripts: bool = False) -> list[Element]: ...
+    def select_first(
+        self, css: str, *, include_scripts: bool = False
+    ) -> Element | None: ...
+    def find(self, css: str, *, include_scripts: bool = False) -> Element | None: ...
+    def select_one(
+        self, css: str, *, include_scripts: bool = False
+    ) -> Element | None: ...
+    def find_all(self, css: str, *, include_scripts: bool = False) -> list[Element]: ...
+    def iter_select(self, css: str) -> SelectIter: ...
+    def css(self, css: str, *, include_scripts: bool = False) -> list[Element]: ...
     def xpath(self, expr: str) -> list[Element]: ...
     def xpath_first(self, expr: str) -> Element | None: ...
+    def as_table(self) -> list[dict[str, str]]: ...
+    def table_matrix(self) -> list[list[str]]: ...
     def to_dict(self) -> ElementDict: ...
+    def to_json(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+    def __reduce__(self) -> tuple[Any, ...]: ...
+    def __copy__(self) -> Element: ...
+    def __deepcopy__(self, memo: dict[int, Any]) -> Element: ...
+
+class SelectIter(Iterator[Element]):
+    def __iter__(self) -> SelectIter: ...
+    def __next__(self) -> Element: ...
+
+class TextIter(Iterator[tuple[str, str]]):
+    def __iter__(self) -> TextIter: ...
+    def __next__(self) -> tuple[str, str]: ...
+
+class CssSelector:
+    css: str
+
+    def __init__(self, css: str) -> None: ...
+    def __repr__(self) -> str: ...
 
 class Document:
     html: str
     text: str
+    title: str | None
+    base_url: str | None
+    quirks_mode: Literal["no-quirks", "quirks", "limited-quirks"]
+    doctype: str | None
+    closed: bool
 
     def __init__(
         self,
@@ -35,6 +134,8 @@ class Document:
         *,
         max_size_bytes: int | None = ...,
         truncate_on_limit: bool = False,
+        base_url: str | None = None,
+        strict: bool = False,
     ) -> None: ...
     @classmethod
     def from_html(
@@ -43,11 +144,163 @@ class Document:
         *,
         max_size_bytes: int | None = ...,
         truncate_on_limit: bool = False,
+        base_url: str | None = None,
+        strict: bool = False,
     ) -> Document: ...
-    def select(self, css: str) -> list[Element]: ...
-    def select_first(self, css: str) -> Element | None: ...
-    def find(self, css: str) -> Element | None: ...
-    def css(self, css: str) -> list[Element]: ...
+    @classmethod
+    def parse_fragment(
+        cls,
+        html: str,
+        *,
+        max_size_bytes: int | None = ...,
+        truncate_on_limit: bool = False,
+        base_url: str | None = None,
+    ) -> Document: ...
+    @classmethod
+    def parse_xml(
+        cls,
+        xml: str,
+        *,
+        max_size_bytes: int | None = ...,
+        truncate_on_limit: bool = False,
+        base_url: str | None = None,
+    ) -> Document: ...
+    @classmethod
+    def from_file(
+        cls,
+        path: str | os.PathLike[str],
+        encoding: str | None = None,
+        *,
+        max_size_bytes: int | None = ...,
+        truncate_on_limit: bool = False,
+        base_url: str | None = None,
+    ) -> Document: ...
+    # Without `encoding`: BOM, then `<meta charset>`, then UTF-8, falling back to
+    # Windows-1252 when the bytes are not valid UTF-8.
+    @classmethod
+    def from_bytes(
+        cls,
+        data: bytes,
+        encoding: str | None = None,
+        *,
+        max_size_bytes: int | None = ...,
+        truncate_on_limit: bool = False,
+        base_url: str | None = None,
+        decompress: Literal["gzip", "deflate", "br", "auto"] | None = None,
+    ) -> Document: ...
+    @classmethod
+    def from_reader(
+        cls,
+        reader: SupportsRead,
+        encoding: str | None = None,
+        *,
+        max_size_bytes: int | None = ...,
+        truncate_on_limit: bool = False,
+        base_url: str | None = None,
+    ) -> Document: ...
+    def get_text(
+        self,
+        separator: str = " ",
+        strip: bool = False,
+        *,
+        include_scripts: bool = False,
+        normalize: bool = False,
+        form: Literal["NFC", "NFD", "NFKC", "NFKD"] | None = None,
+    ) -> str: ...
+    def iter_text(
+        self, *, strip: bool = False, include_scripts: bool = False
+    ) -> TextIter: ...
+    def to_text(self) -> str: ...
+    def to_markdown(self) -> str: ...
+    def word_count(self) -> int: ...
+    def reading_time_minutes(self, wpm: int = 200) -> float: ...
+    def text_excluding(self, exclude: Sequence[str]) -> str: ...
+    def sanitize(
+        self,
+        allowed_tags: Sequence[str] | None = None,
+        allowed_attrs: Sequence[str] | None = None,
+    ) -> str: ...
+    def prettify(self, indent: int = 2) -> str: ...
+    def minify(self) -> str: ...
+    def to_html(self) -> str: ...
+    def to_tree(self) -> dict[str, Any]: ...
+    def errors(self) -> list[str]: ...
+    def content_hash(self) -> str: ...
+    def structure_hash(self) -> str: ...
+    def select(
+        self,
+        css: str,
+        *,
+        tags: Sequence[str] | None = None,
+        include_scripts: bool = False,
+    ) -> list[Element]: ...
+    def select_first(
+        self, css: str, *, include_scripts: bool = False
+    ) -> Element | None: ...
+    def find(self, css: str, *, include_scripts: bool = False) -> Element | None: ...
+    def select_one(
+        self, css: str, *, include_scripts: bool = False
+    ) -> Element | None: ...
+    def find_all(self, css: str, *, include_scripts: bool = False) -> list[Element]: ...
+    def pluck(
+        self, container_css: str, field_css: str, attr: str | None = None
+    ) -> list[str | None]: ...
+    def select_in(self, container_css: str, field_css: str) -> list[list[Element]]: ...
+    def select_templates(self, css: str) -> list[Element]: ...
+    def records(
+        self,
+        container_css: str,
+        fields: Mapping[str, tuple[str, str | None]],
+    ) -> list[dict[str, str | None]]: ...
+    def select_unique(self, css: str, by: str) -> list[Element]: ...
+    def select_csv(self, css: str, columns: Sequence[str]) -> str: ...
+    def select_map(self, selectors: Mapping[str, str]) -> dict[str, list[Element]]: ...
+    def select_limit(self, css: str, limit: int) -> list[Element]: ...
+    def select_slice(
+        self, css: str, offset: int, limit: int | None = None
+    ) -> list[Element]: ...
+    def nth(self, css: str, index: int) -> Element | None: ...
+    def last(self, css: str) -> Element | None: ...
+    def select_containing(
+        self, css: str, substring: str, case_insensitive: bool = False
+    ) -> list[Element]: ...
+    def select_text_regex(self, css: str, pattern: str) -> list[Element]: ...
+    def select_json(self, css: str) -> str: ...
+    def count(self, css: str) -> int: ...
+    def root(self) -> Element: ...
+    def walk(self) -> list[Element]: ...
+    def comments(self) -> list[str]: ...
+    def exists(self, css: str) -> bool: ...
+    def by_id(self, id: str) -> Element | None: ...
+    def by_class(self, name: str) -> list[Element]: ...
+    def by_tag(self, name: str) -> list[Element]: ...
+    def select_attr_regex(
+        self, tag: str | None, name: str, pattern: str
+    ) -> list[Element]: ...
+    def having_attr(self, name: str, non_empty: bool = False) -> list[Element]: ...
+    def find_by_attrs(
+        self, tag: str | None = None, attrs: Mapping[str, str] | None = None
+    ) -> list[Element]: ...
+    def jsonld(self, *, errors: list[str] | None = None) -> list[Any]: ...
+    def meta(self) -> dict[str, str]: ...
+    def opengraph(self) -> dict[str, str]: ...
+    def emails(self) -> list[str]: ...
+    def phones(self) -> list[str]: ...
+    def lang(self) -> str | None: ...
+    def canonical_url(self, base_url: str | None = None) -> str | None: ...
+    def charset(self) -> str | None: ...
+    def forms(self) -> list[FormDict]: ...
+    def tables(self) -> list[list[dict[str, str]]]: ...
+    def links(self, *, absolute: bool = False) -> list[tuple[str, str]]: ...
+    def images(self, base_url: str | None = None) -> list[dict[str, str]]: ...
+    def extract_urls(self, base_url: str | None = None) -> list[str]: ...
+    def select_attr(self, css: str, name: str) -> list[str | None]: ...
+    def select_attrs(
+        self, css: str, names: Sequence[str]
+    ) -> list[dict[str, str | None]]: ...
+    def css(self, css: str, *, include_scripts: bool = False) -> list[Element]: ...
+    def select_compiled(self, selector: CssSelector) -> list[Element]: ...
+    def find_compiled(self, selector: CssSelector) -> Element | None: ...
     def xpath(self, expr: str) -> list[Element]: ...
     def xpath_first(self, expr: str) -> Element | None: ...
     def close(self) -> None: ...
@@ -55,11 +308,49 @@ class Document:
     def __exit__(
         self, exc_type: type[BaseException] | None, exc: BaseException | None, traceback
     ) -> None: ...
+    def __reduce__(self) -> tuple[Any, ...]: ...
+    def __copy__(self) -> Document: ...
+    def __deepcopy__(self, memo: dict[int, Any]) -> Document: ...
     def __repr__(self) -> str: ...
 
 def parse(
-    html: str, *, max_size_bytes: int | None = ..., truncate_on_limit: bool = False
+    html: str,
+    *,
+    max_size_bytes: int | None = ...,
+    truncate_on_limit: bool = False,
+    base_url: str | None = None,
+    fragment: bool = False,
+) -> Document: ...
+def parse_many(
+    htmls: Sequence[str],
+    *,
+    max_size_bytes: int | None = ...,
+    truncate_on_limit: bool = False,
+    base_url: str | None = None,
+    fragment: bool = False,
+) -> list[Document]: ...
+def parse_streaming(
+    html: str,
+    on_start: Callable[[str, dict[str, str]], object] | None = None,
+    on_text: Callable[[str], object] | None = None,
+    on_end: Callable[[str], object] | None = None,
+    *,
+    max_size_bytes: int | None = ...,
+    truncate_on_limit: bool = False,
+) -> bool: ...
+
+# Only present when the extension is built with the `network` feature.
+def fetch(
+    url: str,
+    *,
+    timeout: float = 30.0,
+    headers: Mapping[str, str] | None = None,
+    max_size_bytes: int | None = ...,
+    truncate_on_limit: bool = False,
 ) -> Document: ...
+def diff(old: Document, new: Document, css: str | None = None) -> list[DiffDict]: ...
+def escape_css(ident: str) -> str: ...
+def set_repr_text_width(width: int) -> None: ...
 def select(
     html: str,
     css: str,
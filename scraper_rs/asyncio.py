@@ -5,15 +5,17 @@ including awaitable selectors on nested elements.
 """
 
 import asyncio
+from collections.abc import Iterator
 
 from .scraper_rs import (
     Document as _Document,
     Element as _Element,
-    _select_first_fragment_async,
-    _select_fragment_async,
+    _element_select_async,
+    _element_select_first_async,
     _xpath_first_fragment_async,
     _xpath_fragment_async,
     first_async as _first_async,
+    parse as _parse,
     select_async as _select_async,
     select_first_async as _select_first_async,
     xpath_async as _xpath_async,
@@ -55,29 +57,156 @@ class AsyncElement:
     def html(self) -> str:
         return self._element.html
 
+    @property
+    def outer_html(self) -> str:
+        return self._element.outer_html
+
     @property
     def attrs(self) -> dict[str, str]:
         return self._element.attrs
 
+    @property
+    def id(self) -> str | None:
+        return self._element.id
+
     def attr(self, name: str) -> str | None:
         return self._element.attr(name)
 
     def get(self, name: str, default: str | None = None) -> str | None:
         return self._element.get(name, default)
 
-    async def select(self, css: str) -> list["AsyncElement"]:
-        return _wrap_elements(await _select_fragment_async(self._element.html, css))
+    def abs_attr(self, name: str) -> str | None:
+        return self._element.abs_attr(name)
+
+    def __getitem__(self, name: str) -> str:
+        return self._element[name]
+
+    def __contains__(self, name: object) -> bool:
+        return name in self._element
+
+    def __iter__(self) -> Iterator[str]:
+        return iter(self._element)
+
+    def keys(self) -> list[str]:
+        return self._element.keys()
+
+    def values(self) -> list[str]:
+        return self._element.values()
+
+    def items(self) -> list[tuple[str, str]]:
+        return self._element.items()
+
+    def get_text(
+        self,
+        separator: str = " ",
+        strip: bool = False,
+        *,
+        include_scripts: bool = False,
+        normalize: bool = False,
+        form: str | None = None,
+    ) -> str:
+        return self._element.get_text(
+            separator,
+            strip,
+            include_scripts=include_scripts,
+            normalize=normalize,
+            form=form,
+        )
+
+    def to_text(self) -> str:
+        return self._element.to_text()
+
+    def to_markdown(self) -> str:
+        return self._element.to_markdown()
+
+    def classes(self) -> list[str]:
+        return self._element.classes()
+
+    def has_class(self, name: str) -> bool:
+        return self._element.has_class(name)
+
+    def parent(self) -> "AsyncElement | None":
+        return _wrap_element(self._element.parent())
+
+    def closest(self, css: str) -> "AsyncElement | None":
+        return _wrap_element(self._element.closest(css))
+
+    def matches(self, css: str) -> bool:
+        return self._element.matches(css)
+
+    def find_parent(self, css: str) -> "AsyncElement | None":
+        return _wrap_element(self._element.find_parent(css))
+
+    def find_parents(self, css: str) -> list["AsyncElement"]:
+        return _wrap_elements(self._element.find_parents(css))
+
+    def find_next(self, css: str) -> "AsyncElement | None":
+        return _wrap_element(self._element.find_next(css))
+
+    def find_previous(self, css: str) -> "AsyncElement | None":
+        return _wrap_element(self._element.find_previous(css))
+
+    def lang(self) -> str | None:
+        return self._element.lang()
+
+    def depth(self) -> int | None:
+        return self._element.depth()
+
+    def sibling_index(self) -> int | None:
+        return self._element.sibling_index()
+
+    def css_path(self) -> str | None:
+        return self._element.css_path()
+
+    def children(self) -> list["AsyncElement"]:
+        return _wrap_elements(self._element.children())
+
+    def descendants(self) -> list["AsyncElement"]:
+        return _wrap_elements(self._element.descendants())
 
-    async def select_first(self, css: str) -> "AsyncElement | None":
+    def next_sibling(self) -> "AsyncElement | None":
+        return _wrap_element(self._element.next_sibling())
+
+    def previous_sibling(self) -> "AsyncElement | None":
+        return _wrap_element(self._element.previous_sibling())
+
+    async def select(
+        self, css: str, *, include_scripts: bool = False
+    ) -> list["AsyncElement"]:
+        return _wrap_elements(
+            await _element_select_async(
+                self._element, css, include_scripts=include_scripts
+            )
+        )
+
+    async def select_first(
+        self, css: str, *, include_scripts: bool = False
+    ) -> "AsyncElement | None":
         return _wrap_element(
-            await _select_first_fragment_async(self._element.html, css)
+            await _element_select_first_async(
+                self._element, css, include_scripts=include_scripts
+            )
         )
 
-    async def find(self, css: str) -> "AsyncElement | None":
-        return await self.select_first(css)
+    async def find(
+        self, css: str, *, include_scripts: bool = False
+    ) -> "AsyncElement | None":
+        return await self.select_first(css, include_scripts=include_scripts)
+
+    async def select_one(
+        self, css: str, *, include_scripts: bool = False
+    ) -> "AsyncElement | None":
+        return await self.select_first(css, include_scripts=include_scripts)
+
+    async def find_all(
+        self, css: str, *, include_scripts: bool = False
+    ) -> list["AsyncElement"]:
+        return await self.select(css, include_scripts=include_scripts)
 
-    async def css(self, css: str) -> list["AsyncElement"]:
-        return await self.select(css)
+    async def css(
+        self, css: str, *, include_scripts: bool = False
+    ) -> list["AsyncElement"]:
+        return await self.select(css, include_scripts=include_scripts)
 
     async def xpath(self, expr: str) -> list["AsyncElement"]:
         return _wrap_elements(await _xpath_fragment_async(self._element.html, expr))
@@ -87,9 +216,18 @@ class AsyncElement:
             await _xpath_first_fragment_async(self._element.html, expr)
         )
 
+    def as_table(self) -> list[dict[str, str]]:
+        return self._element.as_table()
+
+    def table_matrix(self) -> list[list[str]]:
+        return self._element.table_matrix()
+
     def to_dict(self) -> dict[str, str | dict[str, str]]:
         return self._element.to_dict()
 
+    def to_json(self) -> str:
+        return self._element.to_json()
+
     def __repr__(self) -> str:
         return repr(self._element)
 
@@ -114,17 +252,70 @@ class AsyncDocument:
     def text(self) -> str:
         return self._document.text
 
-    async def select(self, css: str) -> list[AsyncElement]:
-        return _wrap_elements(await _select_async(self._document.html, css))
+    @property
+    def title(self) -> str | None:
+        return self._document.title
+
+    def get_text(
+        self,
+        separator: str = " ",
+        strip: bool = False,
+        *,
+        include_scripts: bool = False,
+        normalize: bool = False,
+        form: str | None = None,
+    ) -> str:
+        return self._document.get_text(
+            separator,
+            strip,
+            include_scripts=include_scripts,
+            normalize=normalize,
+            form=form,
+        )
+
+    def to_text(self) -> str:
+        return self._document.to_text()
+
+    def to_markdown(self) -> str:
+        return self._document.to_markdown()
+
+    async def select(
+        self, css: str, *, include_scripts: bool = False
+    ) -> list[AsyncElement]:
+        return _wrap_elements(
+            await _select_async(
+                self._document.html, css, include_scripts=include_scripts
+            )
+        )
 
-    async def select_first(self, css: str) -> AsyncElement | None:
-        return _wrap_element(await _select_first_async(self._document.html, css))
+    async def select_first(
+        self, css: str, *, include_scripts: bool = False
+    ) -> AsyncElement | None:
+        return _wrap_element(
+            await _select_first_async(
+                self._document.html, css, include_scripts=include_scripts
+            )
+        )
 
-    async def find(self, css: str) -> AsyncElement | None:
-        return await self.select_first(css)
+    async def find(
+        self, css: str, *, include_scripts: bool = False
+    ) -> AsyncElement | None:
+        return await self.select_first(css, include_scripts=include_scripts)
 
-    async def css(self, css: str) -> list[AsyncElement]:
-        return await self.select(css)
+    async def select_one(
+        self, css: str, *, include_scripts: bool = False
+    ) -> AsyncElement | None:
+        return await self.select_first(css, include_scripts=include_scripts)
+
+    async def find_all(
+        self, css: str, *, include_scripts: bool = False
+    ) -> list[AsyncElement]:
+        return await self.select(css, include_scripts=include_scripts)
+
+    async def css(
+        self, css: str, *, include_scripts: bool = False
+    ) -> list[AsyncElement]:
+        return await self.select(css, include_scripts=include_scripts)
 
     async def xpath(self, expr: str) -> list[AsyncElement]:
         return _wrap_elements(await _xpath_async(self._document.html, expr))
@@ -135,6 +326,10 @@ class AsyncDocument:
     def close(self) -> None:
         self._document.close()
 
+    @property
+    def closed(self) -> bool:
+        return self._document.closed
+
     def __enter__(self) -> "AsyncDocument":
         self._document.__enter__()
         return self
@@ -154,13 +349,14 @@ async def parse(html: str, **kwargs) -> "AsyncDocument":
 
     Args:
         html: The HTML string to parse
-        **kwargs: Additional arguments (max_size_bytes, truncate_on_limit, etc.)
+        **kwargs: Additional arguments (max_size_bytes, truncate_on_limit,
+            base_url, fragment)
 
     Returns:
         An AsyncDocument wrapper
     """
     await asyncio.sleep(0)
-    return AsyncDocument(_Document(html, **kwargs))
+    return AsyncDocument(_parse(html, **kwargs))
 
 
 async def select(html: str, css: str, **kwargs) -> list["AsyncElement"]:
@@ -2,35 +2,118 @@
 
 from __future__ import annotations
 
+from typing import Literal
+
 from . import Document, Element, ElementDict
 
 class AsyncElement:
     tag: str
     text: str
     html: str
+    outer_html: str
     attrs: dict[str, str]
+    id: str | None
     element: Element
 
     def __repr__(self) -> str: ...
     def attr(self, name: str) -> str | None: ...
     def get(self, name: str, default: str | None = ...) -> str | None: ...
-    async def select(self, css: str) -> list[AsyncElement]: ...
-    async def select_first(self, css: str) -> AsyncElement | None: ...
-    async def find(self, css: str) -> AsyncElement | None: ...
-    async def css(self, css: str) -> list[AsyncElement]: ...
+    def abs_attr(self, name: str) -> str | None: ...
+    def __getitem__(self, name: str) -> str: ...
+    def __contains__(self, name: object) -> bool: ...
+    def __iter__(self) -> Iterator[str]: ...
+    def keys(self) -> list[str]: ...
+    def values(self) -> list[str]: ...
+    def items(self) -> list[tuple[str, str]]: ...
+    def get_text(
+        self,
+        separator: str = " ",
+        strip: bool = False,
+        *,
+        include_scripts: bool = False,
+        normalize: bool = False,
+        form: Literal["NFC", "NFD", "NFKC", "NFKD"] | None = None,
+    ) -> str: ...
+    def to_text(self) -> str: ...
+    def to_markdown(self) -> str: ...
+    def classes(self) -> list[str]: ...
+    def has_class(self, name: str) -> bool: ...
+    def parent(self) -> AsyncElement | None: ...
+    def closest(self, css: str) -> AsyncElement | None: ...
+    def matches(self, css: str) -> bool: ...
+    def find_parent(self, css: str) -> AsyncElement | None: ...
+    def find_parents(self, css: str) -> list[AsyncElement]: ...
+    def find_next(self, css: str) -> AsyncElement | None: ...
+    def find_previous(self, css: str) -> AsyncElement | None: ...
+    def lang(self) -> str | None: ...
+    def depth(self) -> int | None: ...
+    def sibling_index(self) -> int | None: ...
+    def css_path(self) -> str | None: ...
+    def children(self) -> list[AsyncElement]: ...
+    def descendants(self) -> list[AsyncElement]: ...
+    def next_sibling(self) -> AsyncElement | None: ...
+    def previous_sibling(self) -> AsyncElement | None: ...
+    async def select(
+        self, css: str, *, include_scripts: bool = False
+    ) -> list[AsyncElement]: ...
+    async def select_first(
+        self, css: str, *, include_scripts: bool = False
+    ) -> AsyncElement | None: ...
+    async def find(
+        self, css: str, *, include_scripts: bool = False
+    ) -> AsyncElement | None: ...
+    async def select_one(
+        self, css: str, *, include_scripts: bool = False
+    ) -> AsyncElement | None: ...
+    async def find_all(
+        self, css: str, *, include_scripts: bool = False
+    ) -> list[AsyncElement]: ...
+    async def css(
+        self, css: str, *, include_scripts: bool = False
+    ) -> list[AsyncElement]: ...
     async def xpath(self, expr: str) -> list[AsyncElement]: ...
     async def xpath_first(self, expr: str) -> AsyncElement | None: ...
+    def as_table(self) -> list[dict[str, str]]: ...
+    def table_matrix(self) -> list[list[str]]: ...
     def to_dict(self) -> ElementDict: ...
+    def to_json(self) -> str: ...
 
 class AsyncDocument:
     html: str
     text: str
+    title: str | None
+    closed: bool
     document: Document
 
-    async def select(self, css: str) -> list[AsyncElement]: ...
-    async def select_first(self, css: str) -> AsyncElement | None: ...
-    async def find(self, css: str) -> AsyncElement | None: ...
-    async def css(self, css: str) -> list[AsyncElement]: ...
+    def get_text(
+        self,
+        separator: str = " ",
+        strip: bool = False,
+        *,
+        include_scripts: bool = False,
+        normalize: bool = False,
+        form: Literal["NFC", "NFD", "NFKC", "NFKD"] | None = None,
+    ) -> str: ...
+    def to_text(self) -> str: ...
+    def to_markdown(self) -> str: ...
+    async def select(
+        self, css: str, *, include_scripts: bool = False
+    ) -> list[AsyncElement]: ...
+    async def select_first(
+        self, css: str, *, include_scripts: bool = False
+    ) -> AsyncElement | None: ...
+    async def find(
+        self, css: str, *, include_scripts: bool = False
+    ) -> AsyncElement | None: ...
+    async def select_one(
+        self, css: str, *, include_scripts: bool = False
+    ) -> AsyncElement | None: ...
+    async def find_all(
+        self, css: str, *, include_scripts: bool = False
+    ) -> list[AsyncElement]: ...
+    async def css(
+        self, css: str, *, include_scripts: bool = False
+    ) -> list[AsyncElement]: ...
     async def xpath(self, expr: str) -> list[AsyncElement]: ...
     async def xpath_first(self, expr: str) -> AsyncElement | None: ...
     def close(self) -> None: ...
@@ -45,6 +128,8 @@ async def parse(
     *,
     max_size_bytes: int | None = ...,
     truncate_on_limit: bool = False,
+    base_url: str | None = None,
+    fragment: bool = False,
 ) -> AsyncDocument: ...
 async def select(
     html: str,
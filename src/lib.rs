@@ -1,15 +1,43 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::cell::{Cell, Ref, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
 
-use pyo3::exceptions::PyValueError;
+use ego_tree::{NodeId, NodeMut, NodeRef, iter::Edge};
+use encoding_rs::{Encoding, WINDOWS_1252};
+use html5ever::TokenizerResult;
+use html5ever::tendril::StrTendril;
+use html5ever::tokenizer::states::RawKind;
+use html5ever::tokenizer::{
+    BufferQueue, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerOpts,
+};
+use html5ever::tree_builder::{ElementFlags, NodeOrText, QuirksMode, TreeSink};
+use html5ever::{Attribute, LocalName, QualName, ns};
+use indexmap::{IndexMap, IndexSet};
+use pyo3::call::PyCallArgs;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyKeyError, PyOSError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::PyDict;
+use pyo3::types::{PyBool, PyBytes, PyDict, PyIterator, PyList, PyString, PyType};
 use pyo3::wrap_pyfunction;
-use scraper::{Html, Selector, element_ref::ElementRef};
+use rayon::prelude::*;
+use regex::Regex;
+use scraper::{Html, HtmlTreeSink, Node, Selector, element_ref::ElementRef};
+use sha2::{Digest, Sha256};
 use sxd_xpath::{Context, Factory, Value, nodeset::Node as XPathNode};
+use unicode_normalization::UnicodeNormalization;
+use url::Url;
+use xml5ever::tendril::TendrilSink;
 
 const DEFAULT_MAX_PARSE_BYTES: usize = 1_073_741_824; // 1 GiB
 
+/// Bytes (or characters) requested per `read()` call by `Document.from_reader`.
+const READER_CHUNK_SIZE: usize = 64 * 1024;
+
 fn effective_max_size(max_size_bytes: Option<usize>) -> usize {
     max_size_bytes.unwrap_or(DEFAULT_MAX_PARSE_BYTES)
 }
@@ -38,6 +66,131 @@ fn ensure_within_size_limit<'a>(
     Ok(Cow::Borrowed(html))
 }
 
+/// How far into the document to look for a `<meta charset>` declaration, as in the
+/// HTML encoding-sniffing prescan.
+const META_CHARSET_PRESCAN_BYTES: usize = 1024;
+
+/// Find a charset declared by a `<meta charset>` or `<meta http-equiv>` tag near the
+/// start of `data`.
+fn sniff_meta_charset(data: &[u8]) -> Option<&'static Encoding> {
+    let head = &data[..data.len().min(META_CHARSET_PRESCAN_BYTES)];
+    let head = head.to_ascii_lowercase();
+    let mut rest = head.as_slice();
+
+    while let Some(start) = find_bytes(rest, b"<meta") {
+        let tag = &rest[start..];
+        let tag = &tag[..find_bytes(tag, b">").unwrap_or(tag.len())];
+        if let Some(encoding) = charset_label(tag).and_then(Encoding::for_label) {
+            // A meta tag cannot declare UTF-16; the spec maps those labels to UTF-8.
+            return Some(encoding.output_encoding());
+        }
+        rest = &rest[start + tag.len()..];
+    }
+    None
+}
+
+/// Extract the value following `charset=` inside a (lowercased) meta tag.
+fn charset_label(tag: &[u8]) -> Option<&[u8]> {
+    let after = &tag[find_bytes(tag, b"charset")? + b"charset".len()..];
+    let after = after
+        .trim_ascii_start()
+        .strip_prefix(b"=")?
+        .trim_ascii_start();
+    let after = after
+        .strip_prefix(b"\"")
+        .or_else(|| after.strip_prefix(b"'"))
+        .unwrap_or(after);
+    let end = after
+        .iter()
+        .position(|b| matches!(b, b'"' | b'\'' | b';' | b'/' | b'>') || b.is_ascii_whitespace())
+        .unwrap_or(after.len());
+    (end > 0).then(|| &after[..end])
+}
+
+/// The encoding named by the `charset` parameter of a `Content-Type` header,
+/// if it is a known label.
+#[cfg(feature = "network")]
+fn content_type_charset(content_type: &str) -> Option<String> {
+    let content_type = content_type.to_ascii_lowercase();
+    let label = charset_label(content_type.as_bytes())?;
+    Encoding::for_label(label).map(|encoding| encoding.name().to_string())
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Decode raw HTML bytes to a string.
+///
+/// An explicit `encoding` label wins; otherwise a byte-order mark, then a
+/// `<meta charset>` declaration, are used. Undeclared input is read as UTF-8 and
+/// falls back to Windows-1252 when it is not valid UTF-8.
+fn decode_html_bytes<'a>(data: &'a [u8], encoding: Option<&str>) -> PyResult<Cow<'a, str>> {
+    if let Some(label) = encoding {
+        let encoding = Encoding::for_label(label.as_bytes())
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown encoding {label:?}")))?;
+        return Ok(encoding.decode_with_bom_removal(data).0);
+    }
+
+    if let Some((encoding, bom_len)) = Encoding::for_bom(data) {
+        return Ok(encoding.decode_without_bom_handling(&data[bom_len..]).0);
+    }
+
+    if let Some(encoding) = sniff_meta_charset(data) {
+        return Ok(encoding.decode_without_bom_handling(data).0);
+    }
+
+    match std::str::from_utf8(data) {
+        Ok(html) => Ok(Cow::Borrowed(html)),
+        Err(_) => Ok(WINDOWS_1252.decode_without_bom_handling(data).0),
+    }
+}
+
+/// Inflate `data` compressed with `method` (`"gzip"`, `"deflate"`, `"br"`, or
+/// `"auto"` to sniff gzip and zlib headers and pass anything else through).
+///
+/// At most `limit` bytes are inflated, so a decompression bomb cannot exhaust
+/// memory before the parse size limit is checked.
+fn decompress_bytes<'a>(data: &'a [u8], method: &str, limit: usize) -> PyResult<Cow<'a, [u8]>> {
+    let method = match method {
+        "auto" if data.starts_with(&[0x1f, 0x8b]) => "gzip",
+        "auto" if is_zlib_header(data) => "deflate",
+        "auto" => return Ok(Cow::Borrowed(data)),
+        "gzip" | "deflate" | "br" => method,
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "Unknown decompression {method:?}: expected \"gzip\", \"deflate\", \"br\" or \"auto\""
+            )));
+        }
+    };
+    let limit = u64::try_from(limit).unwrap_or(u64::MAX);
+    let inflate = |reader: &mut dyn Read| {
+        let mut out = Vec::new();
+        reader.take(limit).read_to_end(&mut out).map(|_| out)
+    };
+    let inflated = match method {
+        "gzip" => inflate(&mut flate2::read::MultiGzDecoder::new(data)),
+        // HTTP "deflate" is meant to be zlib-wrapped, but some servers send a
+        // raw deflate stream instead.
+        "deflate" if is_zlib_header(data) => inflate(&mut flate2::read::ZlibDecoder::new(data)),
+        "deflate" => inflate(&mut flate2::read::DeflateDecoder::new(data)),
+        _ => inflate(&mut brotli_decompressor::Decompressor::new(data, 4096)),
+    };
+    inflated
+        .map(Cow::Owned)
+        .map_err(|e| PyValueError::new_err(format!("Failed to decompress {method} data: {e}")))
+}
+
+/// Whether `data` starts with a zlib (RFC 1950) header using deflate.
+fn is_zlib_header(data: &[u8]) -> bool {
+    matches!(data, [cmf, flg, ..] if cmf & 0x0f == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0)
+}
+
+/// Characters of text shown by `Element.__repr__`; see `set_repr_text_width`.
+static REPR_TEXT_WIDTH: AtomicUsize = AtomicUsize::new(40);
+
 /// Tiny helper to truncate text in __repr__.
 fn truncate_for_repr(s: &str, max_chars: usize) -> String {
     let mut out = String::new();
@@ -51,469 +204,5015 @@ fn truncate_for_repr(s: &str, max_chars: usize) -> String {
     out
 }
 
-/// A single HTML element returned by a CSS selection.
+/// A parsed `scraper` tree shared by a `Document` and the elements selected from it.
 ///
-/// This is a *snapshot* of an element: it stores tag, text, inner HTML
-/// and attributes, all as owned data, so there are no lifetime issues
-/// when used from Python.
-#[pyclass(module = "scraper_rs")]
+/// The tree sits behind a mutex so that node-backed `Element`s stay `Send` and
+/// can outlive the `Document` (or its `close()`) that produced them.
+struct DocumentTree {
+    html: Mutex<Html>,
+    /// Base for resolving relative URLs: the `base_url` option combined with any
+    /// `<base href>` in the document.
+    base_url: Option<Url>,
+    /// Number of edits made through `Element.remove`, `set_attr` and
+    /// `remove_attr`, so the
+    /// owning `Document` can tell when its source HTML and XPath tree are stale.
+    edits: AtomicUsize,
+}
+
+impl DocumentTree {
+    fn new(html: Html, base_url: Option<Url>) -> Arc<Self> {
+        Arc::new(Self {
+            html: Mutex::new(html),
+            base_url,
+            edits: AtomicUsize::new(0),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Html> {
+        self.html.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn edits(&self) -> usize {
+        self.edits.load(Ordering::Relaxed)
+    }
+}
+
+/// A reference to an element node inside a shared `DocumentTree`.
 #[derive(Clone)]
-pub struct Element {
+struct NodeHandle {
+    tree: Arc<DocumentTree>,
+    id: NodeId,
+    /// Whether `text` keeps script and style contents (`select(include_scripts=True)`).
+    include_scripts: bool,
+}
+
+impl NodeHandle {
+    /// Run `f` with the live `ElementRef` for this node.
+    fn with<R>(&self, f: impl FnOnce(ElementRef<'_>) -> R) -> R {
+        let html = self.tree.lock();
+        let node = html
+            .tree
+            .get(self.id)
+            .expect("element node missing from tree");
+        f(ElementRef::wrap(node).expect("node handle does not point at an element"))
+    }
+}
+
+/// Owned element data for results that do not come from the `scraper` tree (XPath).
+#[derive(Clone)]
+struct ElementSnapshot {
     tag: String,
     text: String,
     inner_html: String,
-    attrs: HashMap<String, String>,
+    outer_html: String,
+    attrs: IndexMap<String, String>,
+    base_url: Option<Url>,
+}
+
+/// Pickled form of an Element: tag, text, inner HTML, outer HTML, attributes
+/// and base URL.
+type ElementState = (
+    String,
+    String,
+    String,
+    String,
+    IndexMap<String, String>,
+    Option<String>,
+);
+
+#[derive(Clone)]
+enum ElementSource {
+    Node(NodeHandle),
+    Snapshot(Box<ElementSnapshot>),
+}
+
+/// A single HTML element returned by a CSS selection.
+///
+/// CSS results hold a reference to their node in the parsed tree and compute
+/// `text`, `html` and `attrs` lazily when read. XPath results are owned
+/// snapshots of the same data. Either way there are no lifetime issues when
+/// used from Python, and elements stay valid after their `Document` is closed.
+#[pyclass(module = "scraper_rs")]
+#[derive(Clone)]
+pub struct Element {
+    source: ElementSource,
+    /// `__hash__`, fixed the first time it is taken so that editing the node
+    /// (`set_attr`, `remove`) cannot move the element within a set or dict.
+    hash: OnceLock<u64>,
+}
+
+impl Element {
+    /// Run `f` on this element, which must be a `<table>`; `method` names the
+    /// caller in the error message.
+    fn with_table<R: Default>(&self, method: &str, f: fn(ElementRef<'_>) -> R) -> PyResult<R> {
+        let tag = self.tag();
+        if tag != "table" {
+            return Err(PyValueError::new_err(format!(
+                "{method}() requires a <table> element, got <{tag}>"
+            )));
+        }
+        Ok(match &self.source {
+            ElementSource::Node(node) => node.with(f),
+            ElementSource::Snapshot(snapshot) => {
+                let fragment = Html::parse_fragment(&snapshot.outer_html);
+                let selector = Selector::parse("table").expect("static selector");
+                fragment.select(&selector).next().map(f).unwrap_or_default()
+            }
+        })
+    }
+
+    fn from_ref(tree: &Arc<DocumentTree>, el: ElementRef<'_>) -> Self {
+        Self {
+            source: ElementSource::Node(NodeHandle {
+                tree: Arc::clone(tree),
+                id: el.id(),
+                include_scripts: false,
+            }),
+            hash: OnceLock::new(),
+        }
+    }
+
+    /// This element with `text`, `to_dict` and `to_json` keeping script and
+    /// style contents when `include_scripts` is set.
+    fn including_scripts(mut self, include_scripts: bool) -> Self {
+        if let ElementSource::Node(node) = &mut self.source {
+            node.include_scripts = include_scripts;
+        }
+        self
+    }
+
+    /// Select descendants of this element.
+    ///
+    /// Node-backed elements search their live subtree; XPath snapshots fall back
+    /// to re-parsing their inner HTML as a fragment.
+    fn select_with(&self, selector: &Selector) -> Vec<Element> {
+        match &self.source {
+            ElementSource::Node(node) => node.with(|el| {
+                el.select(selector)
+                    .map(|found| Element::from_ref(&node.tree, found))
+                    .collect()
+            }),
+            ElementSource::Snapshot(snapshot) => select_fragment_with(
+                &snapshot.inner_html,
+                selector,
+                snapshot.base_url.clone(),
+                usize::MAX,
+            ),
+        }
+    }
+
+    /// Map this node to a related element (parent, sibling, ...) in the same tree.
+    ///
+    /// XPath snapshots are detached from any tree, so they have no relatives.
+    fn related(
+        &self,
+        f: impl for<'a> FnOnce(ElementRef<'a>) -> Option<ElementRef<'a>>,
+    ) -> Option<Element> {
+        match &self.source {
+            ElementSource::Node(node) => {
+                node.with(|el| f(el).map(|found| Element::from_ref(&node.tree, found)))
+            }
+            ElementSource::Snapshot(_) => None,
+        }
+    }
+
+    /// Apply `f` to this element's node in the parsed tree and record the edit.
+    ///
+    /// `method` names the caller in the error raised for snapshots (XPath
+    /// results and unpickled elements), which have no tree to edit.
+    fn edit(
+        &self,
+        method: &str,
+        f: impl FnOnce(NodeMut<'_, Node>) -> PyResult<()>,
+    ) -> PyResult<()> {
+        let ElementSource::Node(node) = &self.source else {
+            return Err(PyValueError::new_err(format!(
+                "{method}() needs an element from a CSS selection, not an XPath or unpickled snapshot"
+            )));
+        };
+        let mut html = node.tree.lock();
+        f(html
+            .tree
+            .get_mut(node.id)
+            .expect("element node missing from tree"))?;
+        node.tree.edits.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Like `related`, but for a list of elements (children, descendants, ...).
+    fn related_all(
+        &self,
+        f: impl for<'a> FnOnce(ElementRef<'a>) -> Vec<ElementRef<'a>>,
+    ) -> Vec<Element> {
+        match &self.source {
+            ElementSource::Node(node) => node.with(|el| {
+                f(el)
+                    .into_iter()
+                    .map(|found| Element::from_ref(&node.tree, found))
+                    .collect()
+            }),
+            ElementSource::Snapshot(_) => Vec::new(),
+        }
+    }
+
+    fn attr_with(&self, name: &str) -> Option<String> {
+        match &self.source {
+            ElementSource::Node(node) => node.with(|el| el.attr(name).map(str::to_string)),
+            ElementSource::Snapshot(snapshot) => snapshot.attrs.get(name).cloned(),
+        }
+    }
+
+    /// Attribute `(name, value)` pairs in source order.
+    fn attr_pairs(&self) -> Vec<(String, String)> {
+        match &self.source {
+            ElementSource::Node(node) => node.with(|el| {
+                el.value()
+                    .attrs()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect()
+            }),
+            ElementSource::Snapshot(snapshot) => snapshot
+                .attrs
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+        }
+    }
+
+    /// The `to_dict` fields as a JSON object, attributes in source order.
+    fn json_value(&self) -> serde_json::Value {
+        let attrs = self
+            .attr_pairs()
+            .into_iter()
+            .map(|(name, value)| (name, serde_json::Value::String(value)))
+            .collect();
+        serde_json::json!({
+            "tag": self.tag(),
+            "text": self.normalized_text(),
+            "html": self.html(),
+            "attrs": serde_json::Value::Object(attrs),
+        })
+    }
+
+    /// The values `__eq__` and `__hash__` compare: tag, text, inner HTML and
+    /// attributes sorted by name.
+    fn value_key(&self) -> (String, String, String, Vec<(String, String)>) {
+        let mut attrs = self.attr_pairs();
+        attrs.sort();
+        (self.tag(), self.normalized_text(), self.html(), attrs)
+    }
+
+    /// `to_text` / `to_markdown` output for this element.
+    fn render(&self, markdown: bool) -> String {
+        match &self.source {
+            ElementSource::Node(node) => node.with(|el| TextRenderer::render(el, markdown)),
+            ElementSource::Snapshot(snapshot) => {
+                let fragment = Html::parse_fragment(&snapshot.outer_html);
+                TextRenderer::render(fragment.root_element(), markdown)
+            }
+        }
+    }
+
+    fn base_url(&self) -> Option<&Url> {
+        match &self.source {
+            ElementSource::Node(node) => node.tree.base_url.as_ref(),
+            ElementSource::Snapshot(snapshot) => snapshot.base_url.as_ref(),
+        }
+    }
 }
 
 #[pymethods]
 impl Element {
     /// Tag name of the element (e.g. "div", "a").
     #[getter]
-    pub fn tag(&self) -> &str {
-        &self.tag
+    pub fn tag(&self) -> String {
+        match &self.source {
+            ElementSource::Node(node) => node.with(|el| el.value().name().to_string()),
+            ElementSource::Snapshot(snapshot) => snapshot.tag.clone(),
+        }
     }
 
     /// Normalized text content of the element.
-    #[getter]
-    pub fn text(&self) -> &str {
-        &self.text
+    ///
+    /// Script and style contents are skipped unless the element came from
+    /// `Document.select(..., include_scripts=True)`.
+    #[getter(text)]
+    pub fn normalized_text(&self) -> String {
+        match &self.source {
+            ElementSource::Node(node) if node.include_scripts => {
+                node.with(|el| collapse_text(el, true))
+            }
+            ElementSource::Node(node) => node.with(element_text),
+            ElementSource::Snapshot(snapshot) => snapshot.text.clone(),
+        }
     }
 
     /// Inner HTML of the element (children only, not the outer tag).
     #[getter]
-    pub fn html(&self) -> &str {
-        &self.inner_html
+    pub fn html(&self) -> String {
+        match &self.source {
+            ElementSource::Node(node) => node.with(|el| el.inner_html()),
+            ElementSource::Snapshot(snapshot) => snapshot.inner_html.clone(),
+        }
     }
 
-    /// Mapping of HTML attributes, e.g. {"href": "...", "class": "..."}.
+    /// Outer HTML of the element (the element's own tag, attributes and children).
     #[getter]
-    pub fn attrs(&self) -> HashMap<String, String> {
-        self.attrs.clone()
+    pub fn outer_html(&self) -> String {
+        match &self.source {
+            ElementSource::Node(node) => node.with(|el| el.html()),
+            ElementSource::Snapshot(snapshot) => snapshot.outer_html.clone(),
+        }
     }
 
-    /// Return the value of a single attribute, or None if it doesn't exist.
-    pub fn attr(&self, name: &str) -> Option<String> {
-        self.attrs.get(name).cloned()
+    /// Text of the element with a custom separator between text nodes.
+    ///
+    /// Unlike `text`, whitespace is not collapsed. With `strip=True` each text
+    /// node is trimmed and empty ones are skipped. Text inside nested `script`,
+    /// `style`, `template` and `noscript` elements is left out unless
+    /// `include_scripts=True`. `normalize=True` collapses runs of Unicode
+    /// whitespace (NBSP, thin spaces, ...) inside each text node into a single
+    /// space, and `form` applies a Unicode normalization form to the result:
+    ///
+    ///     address.get_text("\n", strip=True)
+    ///     cell.get_text(strip=True, normalize=True, form="NFC")
+    #[pyo3(signature = (
+        separator=" ",
+        strip=false,
+        *,
+        include_scripts=false,
+        normalize=false,
+        form=None,
+    ))]
+    pub fn get_text(
+        &self,
+        separator: &str,
+        strip: bool,
+        include_scripts: bool,
+        normalize: bool,
+        form: Option<&str>,
+    ) -> PyResult<String> {
+        let text = match &self.source {
+            ElementSource::Node(node) => {
+                node.with(|el| join_text(el, separator, strip, include_scripts, normalize))
+            }
+            ElementSource::Snapshot(snapshot) => {
+                let fragment = Html::parse_fragment(&snapshot.inner_html);
+                join_text(
+                    fragment.root_element(),
+                    separator,
+                    strip,
+                    include_scripts,
+                    normalize,
+                )
+            }
+        };
+        apply_normalization_form(text, form)
     }
 
-    /// Convenience: behave like dict.get(key, default).
-    pub fn get(&self, name: &str, default: Option<String>) -> Option<String> {
-        self.attrs.get(name).cloned().or(default)
+    /// Readable plain text of the element, formatted like `Document.to_text`.
+    pub fn to_text(&self) -> String {
+        self.render(false)
     }
 
-    /// Select elements inside this element's inner HTML using a CSS selector.
+    /// The element converted to Markdown, like `Document.to_markdown`.
     ///
-    ///     item = doc.find(".item")
-    ///     links = item.select("a[href]")
-    pub fn select(&self, css: &str) -> PyResult<Vec<Element>> {
-        select_fragment(&self.inner_html, css)
+    ///     body = doc.find("article").to_markdown()
+    pub fn to_markdown(&self) -> String {
+        self.render(true)
     }
 
-    /// Return the first matching descendant element, or None if nothing matches.
-    pub fn select_first(&self, css: &str) -> PyResult<Option<Element>> {
-        Ok(self.select(css)?.into_iter().next())
+    /// Attribute `name` resolved to an absolute URL.
+    ///
+    /// Relative values are joined against the document's base URL (the
+    /// `base_url` option and any `<base href>`); absolute values pass through.
+    /// Returns None when the attribute is missing or cannot be resolved.
+    ///
+    ///     doc = Document(html, base_url="https://example.com/docs/")
+    ///     doc.find("a").abs_attr("href")  # "https://example.com/docs/page"
+    pub fn abs_attr(&self, name: &str) -> Option<String> {
+        let value = self.attr_with(name)?;
+        resolve_url(self.base_url(), &value)
     }
 
-    /// Return the first matching descendant element, or None if nothing matches.
-    pub fn find(&self, css: &str) -> PyResult<Option<Element>> {
-        self.select_first(css)
+    /// Value of the `id` attribute, or None if it is not set.
+    #[getter]
+    pub fn id(&self) -> Option<String> {
+        self.attr_with("id")
     }
 
-    /// Alias for `select(css)`.
-    pub fn css(&self, css: &str) -> PyResult<Vec<Element>> {
-        self.select(css)
+    /// Mapping of HTML attributes, e.g. {"href": "...", "class": "..."}.
+    ///
+    /// Keys are in the order the attributes appear in the source HTML.
+    #[getter]
+    pub fn attrs(&self) -> IndexMap<String, String> {
+        match &self.source {
+            ElementSource::Node(node) => node.with(element_attrs),
+            ElementSource::Snapshot(snapshot) => snapshot.attrs.clone(),
+        }
     }
 
-    /// Evaluate an XPath expression against this element's children.
-    ///
-    /// The XPath runs inside this element; expressions must return element nodes.
-    pub fn xpath(&self, expr: &str) -> PyResult<Vec<Element>> {
-        evaluate_fragment_xpath(&self.inner_html, expr)
+    /// Return the value of a single attribute, or None if it doesn't exist.
+    pub fn attr(&self, name: &str) -> Option<String> {
+        self.attr_with(name)
     }
 
-    /// Return the first matching descendant for an XPath expression, or None.
-    pub fn xpath_first(&self, expr: &str) -> PyResult<Option<Element>> {
-        Ok(self.xpath(expr)?.into_iter().next())
+    /// Convenience: behave like dict.get(key, default).
+    pub fn get(&self, name: &str, default: Option<String>) -> Option<String> {
+        self.attr_with(name).or(default)
     }
 
-    /// Convert this element to a plain dict.
-    ///
-    /// {
-    ///   "tag": str,
-    ///   "text": str,
-    ///   "html": str,
-    ///   "attrs": {str: str}
-    /// }
-    pub fn to_dict(&self, py: Python<'_>) -> PyResult<Py<PyDict>> {
-        let dict = PyDict::new(py);
-        dict.set_item("tag", &self.tag)?;
-        dict.set_item("text", &self.text)?;
-        dict.set_item("html", &self.inner_html)?;
-        dict.set_item("attrs", &self.attrs)?;
-        Ok(dict.into())
+    /// `el["href"]`: the attribute value, raising KeyError when it is missing.
+    fn __getitem__(&self, name: &str) -> PyResult<String> {
+        self.attr_with(name)
+            .ok_or_else(|| PyKeyError::new_err(name.to_string()))
     }
 
-    /// Representation of the element for debugging.
-    fn __repr__(&self) -> String {
-        let text_preview = truncate_for_repr(self.text.trim(), 40);
-        format!("<Element tag='{}' text={}>", self.tag, text_preview)
+    /// `"href" in el`: whether the attribute is set.
+    fn __contains__(&self, name: &str) -> bool {
+        self.attr_with(name).is_some()
     }
-}
 
-/// Convert a scraper ElementRef into our owned Element snapshot.
-fn snapshot_element(el: ElementRef<'_>) -> Element {
-    let tag = el.value().name().to_string();
+    /// Iterate over attribute names, like iterating `attrs`.
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyIterator>> {
+        PyList::new(py, self.keys())?.try_iter()
+    }
 
-    let text = el
-        .text()
-        .collect::<Vec<_>>()
-        .join(" ")
-        .split_whitespace()
-        .collect::<Vec<_>>()
-        .join(" ");
+    /// Attribute names, in the same order as `values()` and `items()`.
+    pub fn keys(&self) -> Vec<String> {
+        self.attr_pairs()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
 
-    let inner_html = el.inner_html();
+    /// Attribute values, in the same order as `keys()`.
+    pub fn values(&self) -> Vec<String> {
+        self.attr_pairs()
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect()
+    }
 
-    let mut attrs = HashMap::new();
-    for (name, value) in el.value().attrs() {
-        attrs.insert(name.to_string(), value.to_string());
+    /// `(name, value)` attribute pairs, so `dict(el.items())` equals `el.attrs`.
+    pub fn items(&self) -> Vec<(String, String)> {
+        self.attr_pairs()
     }
 
-    Element {
-        tag,
-        text,
-        inner_html,
-        attrs,
+    /// Classes from the `class` attribute, split on ASCII whitespace and deduplicated.
+    pub fn classes(&self) -> Vec<String> {
+        let mut classes: Vec<String> = Vec::new();
+        if let Some(value) = self.attr_with("class") {
+            for class in value.split_ascii_whitespace() {
+                if !classes.iter().any(|existing| existing == class) {
+                    classes.push(class.to_string());
+                }
+            }
+        }
+        classes
     }
-}
 
-fn parse_selector(css: &str) -> PyResult<Selector> {
-    Selector::parse(css)
-        .map_err(|e| PyValueError::new_err(format!("Invalid CSS selector {css:?}: {e:?}")))
-}
+    /// Return True if the `class` attribute contains `name`.
+    pub fn has_class(&self, name: &str) -> bool {
+        self.attr_with("class")
+            .is_some_and(|value| value.split_ascii_whitespace().any(|class| class == name))
+    }
 
-fn select_fragment(html: &str, css: &str) -> PyResult<Vec<Element>> {
-    let selector = parse_selector(css)?;
-    let fragment = Html::parse_fragment(html);
-    Ok(fragment.select(&selector).map(snapshot_element).collect())
-}
+    /// Return the parent element, or None at the document root or for XPath results.
+    ///
+    ///     price = doc.find("span.price")
+    ///     row = price.parent()
+    pub fn parent(&self) -> Option<Element> {
+        self.related(|el| {
+            el.parent()
+                .and_then(ElementRef::wrap)
+                .filter(|parent| !is_fragment_wrapper(*parent))
+        })
+    }
 
-fn normalize_whitespace(text: &str) -> String {
-    text.split_whitespace().collect::<Vec<_>>().join(" ")
-}
+    /// The nearest element matching `css`, starting with this element and walking
+    /// up through its ancestors; None if nothing matches or for XPath results.
+    ///
+    ///     cell = doc.find("td.price")
+    ///     table = cell.closest("table")
+    pub fn closest(&self, css: &str) -> PyResult<Option<Element>> {
+        let selector = parse_selector(css)?;
+        Ok(self.related(|el| {
+            std::iter::once(el)
+                .chain(el.ancestors().filter_map(ElementRef::wrap))
+                .filter(|candidate| !is_fragment_wrapper(*candidate))
+                .find(|candidate| selector.matches(candidate))
+        }))
+    }
 
-fn escape_html(value: &str) -> String {
-    let mut escaped = String::new();
-    for ch in value.chars() {
-        match ch {
-            '&' => escaped.push_str("&amp;"),
-            '<' => escaped.push_str("&lt;"),
-            '>' => escaped.push_str("&gt;"),
-            '"' => escaped.push_str("&quot;"),
-            '\'' => escaped.push_str("&#39;"),
-            _ => escaped.push(ch),
-        }
+    /// The first element after this one in document order that matches `css`,
+    /// like BeautifulSoup's `find_next`. The search covers this element's own
+    /// descendants and everything that follows it, not just siblings. None if
+    /// nothing matches or for XPath results.
+    ///
+    ///     label = doc.select_containing("dt", "Price")[0]
+    ///     value = label.find_next("dd")
+    pub fn find_next(&self, css: &str) -> PyResult<Option<Element>> {
+        let selector = parse_selector(css)?;
+        Ok(self.related(|el| {
+            std::iter::successors(next_in_order(*el), |node| next_in_order(*node))
+                .filter_map(ElementRef::wrap)
+                .find(|candidate| selector.matches(candidate))
+        }))
     }
-    escaped
-}
 
-fn serialize_node_into(buf: &mut String, node: XPathNode<'_>) {
-    if let Some(element) = node.element() {
-        let name = element.name().local_part();
-        buf.push('<');
-        buf.push_str(name);
+    /// The closest element before this one in document order that matches
+    /// `css`, like BeautifulSoup's `find_previous`. Ancestors count as
+    /// preceding. None if nothing matches or for XPath results.
+    ///
+    ///     heading = doc.find("p.note").find_previous("h1, h2, h3")
+    pub fn find_previous(&self, css: &str) -> PyResult<Option<Element>> {
+        let selector = parse_selector(css)?;
+        Ok(self.related(|el| {
+            std::iter::successors(previous_in_order(*el), |node| previous_in_order(*node))
+                .filter_map(ElementRef::wrap)
+                .filter(|candidate| !is_fragment_wrapper(*candidate))
+                .find(|candidate| selector.matches(candidate))
+        }))
+    }
 
-        for attr in element.attributes().iter() {
-            buf.push(' ');
-            buf.push_str(attr.name().local_part());
-            buf.push('=');
-            buf.push('"');
-            buf.push_str(&escape_html(attr.value()));
-            buf.push('"');
+    /// The language this element's content is in: the `lang` attribute of the
+    /// element or its nearest ancestor that has one, falling back to a
+    /// `<meta http-equiv="Content-Language">` pragma.
+    ///
+    /// None when nothing declares a language, when the nearest `lang` is empty
+    /// (HTML's "unknown language"), or for XPath results.
+    ///
+    ///     doc.find("blockquote").lang()  # "fr"
+    pub fn lang(&self) -> Option<String> {
+        match &self.source {
+            ElementSource::Node(node) => node.with(element_lang),
+            ElementSource::Snapshot(_) => None,
         }
+    }
 
-        buf.push('>');
-        for child in element.children() {
-            serialize_node_into(buf, child.into());
+    /// Number of element ancestors: 0 for `<html>` (or a top-level node of a
+    /// fragment), 1 for `<body>`, and so on. None for XPath results.
+    pub fn depth(&self) -> Option<usize> {
+        match &self.source {
+            ElementSource::Node(node) => node.with(|el| {
+                Some(
+                    el.ancestors()
+                        .filter_map(ElementRef::wrap)
+                        .filter(|ancestor| !is_fragment_wrapper(*ancestor))
+                        .count(),
+                )
+            }),
+            ElementSource::Snapshot(_) => None,
         }
-        buf.push_str("</");
-        buf.push_str(name);
-        buf.push('>');
-    } else if let Some(text) = node.text() {
-        buf.push_str(&escape_html(text.text()));
     }
-}
 
-fn serialize_children(node: XPathNode<'_>) -> String {
-    let mut buf = String::new();
-    for child in node.children() {
-        serialize_node_into(&mut buf, child);
+    /// Zero-based position among the parent's element children, ignoring text
+    /// and comment nodes. None for XPath results.
+    ///
+    ///     third = doc.select("li")[2]
+    ///     assert third.sibling_index() == 2
+    pub fn sibling_index(&self) -> Option<usize> {
+        match &self.source {
+            ElementSource::Node(node) => node.with(|el| {
+                Some(
+                    el.prev_siblings()
+                        .filter(|n| n.value().is_element())
+                        .count(),
+                )
+            }),
+            ElementSource::Snapshot(_) => None,
+        }
     }
-    buf
-}
 
-fn collect_text_nodes(node: XPathNode<'_>, out: &mut Vec<String>) {
-    if let Some(text) = node.text() {
-        out.push(text.text().to_string());
+    /// A CSS selector that matches exactly this element, built from its tag,
+    /// classes, `:nth-child()` positions and the nearest unique `id`. None for
+    /// XPath results.
+    ///
+    ///     path = el.css_path()  # "html > body > div.content > ul > li:nth-child(3)"
+    ///     assert doc.select(path)[0].html == el.html
+    pub fn css_path(&self) -> Option<String> {
+        match &self.source {
+            ElementSource::Node(node) => node.with(|el| Some(css_path(el))),
+            ElementSource::Snapshot(_) => None,
+        }
     }
 
-    for child in node.children() {
-        collect_text_nodes(child, out);
+    /// Return True if this element matches `css`, like DOM `Element.matches()`.
+    ///
+    /// Node-backed elements are tested in place, so combinators such as
+    /// `ul > li` see the real ancestors. XPath results are detached from the
+    /// tree and only match selectors about the element itself.
+    ///
+    ///     if el.matches("a[href^='http']"):
+    ///         ...
+    pub fn matches(&self, css: &str) -> PyResult<bool> {
+        let selector = parse_selector(css)?;
+        Ok(match &self.source {
+            ElementSource::Node(node) => node.with(|el| selector.matches(&el)),
+            ElementSource::Snapshot(snapshot) => {
+                let fragment = Html::parse_fragment(&snapshot.outer_html);
+                fragment
+                    .root_element()
+                    .child_elements()
+                    .next()
+                    .is_some_and(|el| selector.matches(&el))
+            }
+        })
     }
-}
 
-fn text_from_node(node: XPathNode<'_>) -> String {
+    /// The nearest ancestor matching `css`, excluding this element itself
+    /// (unlike `closest`), like BeautifulSoup's `find_parent`. None if nothing
+    /// matches or for XPath results.
+    ///
+    ///     row = doc.find("td.price").find_parent("tr")
+    pub fn find_parent(&self, css: &str) -> PyResult<Option<Element>> {
+        let selector = parse_selector(css)?;
+        Ok(self.related(|el| {
+            el.ancestors()
+                .filter_map(ElementRef::wrap)
+                .filter(|ancestor| !is_fragment_wrapper(*ancestor))
+                .find(|ancestor| selector.matches(ancestor))
+        }))
+    }
+
+    /// Every ancestor matching `css`, nearest first. Empty for XPath results.
+    pub fn find_parents(&self, css: &str) -> PyResult<Vec<Element>> {
+        let selector = parse_selector(css)?;
+        Ok(self.related_all(|el| {
+            el.ancestors()
+                .filter_map(ElementRef::wrap)
+                .filter(|ancestor| !is_fragment_wrapper(*ancestor))
+                .filter(|ancestor| selector.matches(ancestor))
+                .collect()
+        }))
+    }
+
+    /// Direct element children, skipping text and comment nodes.
+    pub fn children(&self) -> Vec<Element> {
+        self.related_all(|el| el.child_elements().collect())
+    }
+
+    /// All descendant elements in document order, excluding this element.
+    pub fn descendants(&self) -> Vec<Element> {
+        self.related_all(|el| el.descendent_elements().skip(1).collect())
+    }
+
+    /// The next sibling element, skipping text and comment nodes; None at the end.
+    ///
+    ///     dt = doc.find("dt")
+    ///     dd = dt.next_sibling()
+    pub fn next_sibling(&self) -> Option<Element> {
+        self.related(|el| el.next_siblings().find_map(ElementRef::wrap))
+    }
+
+    /// The previous sibling element, skipping text and comment nodes; None at the start.
+    pub fn previous_sibling(&self) -> Option<Element> {
+        self.related(|el| el.prev_siblings().find_map(ElementRef::wrap))
+    }
+
+    /// Detach this element and its subtree from the document.
+    ///
+    /// Later selections and `Document.to_html()` no longer see it. The removed
+    /// element stays readable but has no parent. The root element cannot be
+    /// removed, and XPath results raise ValueError since they are snapshots.
+    ///
+    ///     for ad in doc.select(".ad, script"):
+    ///         ad.remove()
+    ///     cleaned = doc.to_html()
+    pub fn remove(&self) -> PyResult<()> {
+        self.edit("remove", |mut node| {
+            if node
+                .parent()
+                .is_some_and(|mut parent| !parent.value().is_element())
+            {
+                return Err(PyValueError::new_err("Cannot remove the root element"));
+            }
+            node.detach();
+            Ok(())
+        })
+    }
+
+    /// Set the attribute `name` to `value` in the document tree.
+    ///
+    /// An existing attribute keeps its position; a new one is added last. The
+    /// change is visible through `attrs`, later selections and
+    /// `Document.to_html()`. Names containing whitespace, quotes, `/`, `=` or
+    /// `>` raise ValueError, as do XPath results.
+    ///
+    ///     for a in doc.select("a[href]"):
+    ///         a.set_attr("href", a.abs_attr("href") or "")
+    pub fn set_attr(&self, name: &str, value: &str) -> PyResult<()> {
+        if name.is_empty()
+            || name
+                .chars()
+                .any(|ch| ch.is_whitespace() || matches!(ch, '"' | '\'' | '/' | '=' | '>' | '<'))
+        {
+            return Err(PyValueError::new_err(format!(
+                "Invalid attribute name {name:?}"
+            )));
+        }
+        self.edit("set_attr", |node| {
+            rewrite_attrs(node, |attrs| {
+                let value = StrTendril::from(value);
+                match attrs.iter_mut().find(|attr| is_plain_attr(attr, name)) {
+                    Some(attr) => attr.value = value,
+                    None => attrs.push(Attribute {
+                        name: QualName::new(None, ns!(), LocalName::from(name)),
+                        value,
+                    }),
+                }
+            });
+            Ok(())
+        })
+    }
+
+    /// Remove the attribute `name` from the element in the document tree; a
+    /// missing attribute is ignored. XPath results raise ValueError.
+    ///
+    ///     for el in doc.select("[onclick]"):
+    ///         el.remove_attr("onclick")
+    pub fn remove_attr(&self, name: &str) -> PyResult<()> {
+        self.edit("remove_attr", |node| {
+            rewrite_attrs(node, |attrs| {
+                attrs.retain(|attr| !is_plain_attr(attr, name))
+            });
+            Ok(())
+        })
+    }
+
+    /// Select descendants of this element using a CSS selector.
+    ///
+    /// The search is scoped to this element's subtree, so matches never come
+    /// from sibling elements; `:scope` refers to this element.
+    ///
+    ///     item = doc.find(".item")
+    ///     links = item.select("a[href]")
+    ///
+    /// `include_scripts` behaves as in `Document.select`.
+    #[pyo3(signature = (css, *, include_scripts=false))]
+    pub fn select(&self, css: &str, include_scripts: bool) -> PyResult<Vec<Element>> {
+        let selector = parse_selector(css)?;
+        Ok(self
+            .select_with(&selector)
+            .into_iter()
+            .map(|el| el.including_scripts(include_scripts))
+            .collect())
+    }
+
+    /// Return the first matching descendant element, or None if nothing matches.
+    #[pyo3(signature = (css, *, include_scripts=false))]
+    pub fn select_first(&self, css: &str, include_scripts: bool) -> PyResult<Option<Element>> {
+        let selector = parse_selector(css)?;
+        let found = match &self.source {
+            ElementSource::Node(node) => node.with(|el| {
+                el.select(&selector)
+                    .next()
+                    .map(|found| Element::from_ref(&node.tree, found))
+            }),
+            ElementSource::Snapshot(snapshot) => select_fragment_with(
+                &snapshot.inner_html,
+                &selector,
+                snapshot.base_url.clone(),
+                1,
+            )
+            .into_iter()
+            .next(),
+        };
+        Ok(found.map(|el| el.including_scripts(include_scripts)))
+    }
+
+    /// Return the first matching descendant element, or None if nothing matches.
+    #[pyo3(signature = (css, *, include_scripts=false))]
+    pub fn find(&self, css: &str, include_scripts: bool) -> PyResult<Option<Element>> {
+        self.select_first(css, include_scripts)
+    }
+
+    /// Alias for `select(css)`.
+    #[pyo3(signature = (css, *, include_scripts=false))]
+    pub fn css(&self, css: &str, include_scripts: bool) -> PyResult<Vec<Element>> {
+        self.select(css, include_scripts)
+    }
+
+    /// Alias for `select_first(css)`, named like BeautifulSoup's `select_one`.
+    #[pyo3(signature = (css, *, include_scripts=false))]
+    pub fn select_one(&self, css: &str, include_scripts: bool) -> PyResult<Option<Element>> {
+        self.select_first(css, include_scripts)
+    }
+
+    /// Alias for `select(css)`, named like BeautifulSoup's `find_all`. Only CSS
+    /// selectors are accepted, not BeautifulSoup's tag and attribute filters.
+    #[pyo3(signature = (css, *, include_scripts=false))]
+    pub fn find_all(&self, css: &str, include_scripts: bool) -> PyResult<Vec<Element>> {
+        self.select(css, include_scripts)
+    }
+
+    /// Evaluate an XPath expression against this element's children.
+    ///
+    /// The XPath runs inside this element; expressions must return element nodes.
+    pub fn xpath(&self, expr: &str) -> PyResult<Vec<Element>> {
+        let elements = evaluate_fragment_xpath(&self.html(), expr, usize::MAX)?;
+        Ok(with_base_url(elements, self.base_url()))
+    }
+
+    /// Return the first matching descendant for an XPath expression, or None.
+    ///
+    /// Only the first match is snapshotted.
+    pub fn xpath_first(&self, expr: &str) -> PyResult<Option<Element>> {
+        let elements = evaluate_fragment_xpath(&self.html(), expr, 1)?;
+        Ok(with_base_url(elements, self.base_url()).into_iter().next())
+    }
+
+    /// Serialize the `to_dict` fields as a JSON string.
+    ///
+    ///     print(el.to_json())  # {"tag":"a","text":"...","html":"...","attrs":{...}}
+    pub fn to_json(&self) -> String {
+        self.json_value().to_string()
+    }
+
+    /// Rows of this `<table>` as dicts keyed by the header row.
+    ///
+    /// The first row's cells are the headers; see `Document.tables` for how
+    /// `colspan`, ragged rows and duplicate headers are handled.
+    ///
+    ///     rows = doc.find("table.prices").as_table()
+    pub fn as_table(&self, py: Python<'_>) -> PyResult<Vec<Py<PyDict>>> {
+        let records = self.with_table("as_table", table_records)?;
+        records_to_py(py, records)
+    }
+
+    /// Cells of this `<table>` as a rectangular grid of normalized texts.
+    ///
+    /// Unlike `as_table` no row is treated as a header. A cell with `rowspan`
+    /// or `colspan` fills every grid slot it covers with its text, and short
+    /// rows are padded with empty strings, so all rows have the same length.
+    ///
+    ///     grid = doc.find("table").table_matrix()
+    pub fn table_matrix(&self) -> PyResult<Vec<Vec<String>>> {
+        self.with_table("table_matrix", table_matrix)
+    }
+
+    /// Convert this element to a plain dict.
+    ///
+    /// {
+    ///   "tag": str,
+    ///   "text": str,
+    ///   "html": str,
+    ///   "attrs": {str: str}
+    /// }
+    pub fn to_dict(&self, py: Python<'_>) -> PyResult<Py<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("tag", self.tag())?;
+        dict.set_item("text", self.normalized_text())?;
+        dict.set_item("html", self.html())?;
+        dict.set_item("attrs", self.attrs())?;
+        Ok(dict.into())
+    }
+
+    /// Value-based equality: elements are equal when their tag, text, inner HTML
+    /// and attributes match, wherever (and from whichever document) they came.
+    fn __eq__(&self, other: PyRef<'_, Element>) -> bool {
+        self.value_key() == other.value_key()
+    }
+
+    /// Hash consistent with `__eq__`, so elements can be deduplicated in a set.
+    ///
+    /// The hash is computed from the element's value the first time it is
+    /// taken and then kept, so an element already in a set or dict stays
+    /// findable after `set_attr` or `remove` edits it. Elements selected after
+    /// an edit hash by the edited value.
+    fn __hash__(&self) -> u64 {
+        *self.hash.get_or_init(|| {
+            let mut hasher = DefaultHasher::new();
+            self.value_key().hash(&mut hasher);
+            hasher.finish()
+        })
+    }
+
+    /// Pickle support: elements are pickled as owned snapshots of their content.
+    ///
+    /// An unpickled element keeps `tag`, `text`, `html`, `outer_html`, `attrs`
+    /// and URL resolution, but like XPath results it has no node in a parsed
+    /// tree, so navigation methods return None / [].
+    fn __reduce__<'py>(&self, py: Python<'py>) -> PyResult<(Bound<'py, PyAny>, ElementState)> {
+        let from_state = py.get_type::<Element>().getattr("_from_state")?;
+        Ok((
+            from_state,
+            (
+                self.tag(),
+                self.normalized_text(),
+                self.html(),
+                self.outer_html(),
+                self.attrs(),
+                self.base_url().map(Url::to_string),
+            ),
+        ))
+    }
+
+    /// Rebuild a pickled element; see `__reduce__`.
+    #[classmethod]
+    fn _from_state(
+        _cls: &Bound<'_, PyType>,
+        tag: String,
+        text: String,
+        inner_html: String,
+        outer_html: String,
+        attrs: IndexMap<String, String>,
+        base_url: Option<&str>,
+    ) -> PyResult<Self> {
+        Ok(Element {
+            source: ElementSource::Snapshot(Box::new(ElementSnapshot {
+                tag,
+                text,
+                inner_html,
+                outer_html,
+                attrs,
+                base_url: base_url.map(parse_base_url).transpose()?,
+            })),
+            hash: OnceLock::new(),
+        })
+    }
+
+    /// `copy.copy(el)`. The copy shares the parsed tree with the original, so
+    /// both refer to the same node.
+    fn __copy__(&self) -> Self {
+        self.clone()
+    }
+
+    /// `copy.deepcopy(el)`: a detached snapshot of the element, like an
+    /// unpickled one, so later edits to either side do not affect the other.
+    fn __deepcopy__(&self, _memo: Bound<'_, PyAny>) -> Self {
+        Element {
+            source: ElementSource::Snapshot(Box::new(ElementSnapshot {
+                tag: self.tag(),
+                text: self.normalized_text(),
+                inner_html: self.html(),
+                outer_html: self.outer_html(),
+                attrs: self.attrs(),
+                base_url: self.base_url().cloned(),
+            })),
+            hash: OnceLock::new(),
+        }
+    }
+
+    /// Representation of the element for debugging, with its `id` and `class`
+    /// (quoted and escaped) when present and a text preview cut at
+    /// `set_repr_text_width` characters.
+    fn __repr__(&self) -> String {
+        let width = REPR_TEXT_WIDTH.load(Ordering::Relaxed);
+        let text_preview = truncate_for_repr(self.normalized_text().trim(), width);
+        let mut repr = format!("<Element tag='{}'", self.tag());
+        for name in ["id", "class"] {
+            if let Some(value) = self.attr_with(name) {
+                repr.push_str(&format!(" {name}={value:?}"));
+            }
+        }
+        repr.push_str(&format!(" text={text_preview}>"));
+        repr
+    }
+}
+
+/// Elements whose text is code or inert markup rather than page content.
+const NON_CONTENT_ELEMENTS: &[&str] = &["script", "style", "template", "noscript"];
+
+fn is_non_content_element(name: &str) -> bool {
+    NON_CONTENT_ELEMENTS.contains(&name)
+}
+
+/// Text nodes under `el` in document order.
+///
+/// Unless `include_scripts` is set, the contents of descendant `script`, `style`,
+/// `template` and `noscript` elements are skipped. `el` itself is never skipped, so
+/// the text of a selected `<script>` is still available.
+fn text_parts(el: ElementRef<'_>, include_scripts: bool) -> Vec<&str> {
+    text_parts_excluding(el, include_scripts, &[])
+}
+
+/// `text_parts`, additionally skipping every descendant element that matches one
+/// of `exclude` (with its subtree).
+fn text_parts_excluding<'a>(
+    el: ElementRef<'a>,
+    include_scripts: bool,
+    exclude: &[Selector],
+) -> Vec<&'a str> {
     let mut parts = Vec::new();
-    collect_text_nodes(node, &mut parts);
-    normalize_whitespace(&parts.join(" "))
+    let mut skipping: Option<NodeId> = None;
+    for edge in el.traverse() {
+        match edge {
+            Edge::Open(node) if skipping.is_none() => match node.value() {
+                Node::Text(text) => parts.push(&**text),
+                Node::Element(element)
+                    if node.id() != el.id()
+                        && ((!include_scripts && is_non_content_element(element.name()))
+                            || ElementRef::wrap(node).is_some_and(|child| {
+                                exclude.iter().any(|selector| selector.matches(&child))
+                            })) =>
+                {
+                    skipping = Some(node.id());
+                }
+                _ => {}
+            },
+            Edge::Close(node) if skipping == Some(node.id()) => skipping = None,
+            _ => {}
+        }
+    }
+    parts
 }
 
-fn snapshot_xpath_element(node: XPathNode<'_>) -> PyResult<Element> {
-    let element = node.element().ok_or_else(|| {
-        PyValueError::new_err("XPath expression must return element nodes for conversion")
-    })?;
+/// Normalized text content of a scraper ElementRef.
+fn element_text(el: ElementRef<'_>) -> String {
+    collapse_text(el, false)
+}
 
-    let tag = element.name().local_part().to_string();
-    let text = text_from_node(node);
-    let inner_html = serialize_children(node);
+/// `element_text`, optionally keeping script and style contents.
+fn collapse_text(el: ElementRef<'_>, include_scripts: bool) -> String {
+    text_parts(el, include_scripts)
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-    let mut attrs = HashMap::new();
-    for attr in element.attributes().iter() {
-        attrs.insert(
-            attr.name().local_part().to_string(),
-            attr.value().to_string(),
-        );
+/// Join the text nodes under `el` with `separator`, BeautifulSoup `get_text` style.
+///
+/// With `strip`, each text node is trimmed and whitespace-only nodes are dropped.
+/// With `normalize`, whitespace runs inside each text node become one space.
+fn join_text(
+    el: ElementRef<'_>,
+    separator: &str,
+    strip: bool,
+    include_scripts: bool,
+    normalize: bool,
+) -> String {
+    let parts = text_parts(el, include_scripts).into_iter().map(|part| {
+        if normalize {
+            Cow::Owned(collapse_whitespace(part, char::is_whitespace))
+        } else {
+            Cow::Borrowed(part)
+        }
+    });
+    if strip {
+        parts
+            .filter_map(|part| {
+                let trimmed = part.trim();
+                (!trimmed.is_empty()).then(|| trimmed.to_string())
+            })
+            .collect::<Vec<_>>()
+            .join(separator)
+    } else {
+        parts.collect::<Vec<_>>().join(separator)
     }
+}
 
-    Ok(Element {
-        tag,
-        text,
-        inner_html,
-        attrs,
+/// Replace each run of `is_space` characters in `text` with a single ASCII
+/// space, keeping a leading or trailing run (as one space) so adjacent nodes
+/// stay separated.
+fn collapse_whitespace(text: &str, is_space: impl Fn(char) -> bool) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_space = false;
+    for ch in text.chars() {
+        if is_space(ch) {
+            if !in_space {
+                collapsed.push(' ');
+            }
+            in_space = true;
+        } else {
+            collapsed.push(ch);
+            in_space = false;
+        }
+    }
+    collapsed
+}
+
+/// Apply the Unicode normalization `form` named by `get_text(form=...)`.
+fn apply_normalization_form(text: String, form: Option<&str>) -> PyResult<String> {
+    Ok(match form {
+        None => text,
+        Some("NFC") => text.nfc().collect(),
+        Some("NFD") => text.nfd().collect(),
+        Some("NFKC") => text.nfkc().collect(),
+        Some("NFKD") => text.nfkd().collect(),
+        Some(other) => {
+            return Err(PyValueError::new_err(format!(
+                "Unknown normalization form {other:?}: expected \"NFC\", \"NFD\", \"NFKC\" or \"NFKD\""
+            )));
+        }
     })
 }
 
-fn evaluate_xpath_nodes<'d>(node: XPathNode<'d>, expr: &str) -> PyResult<Vec<XPathNode<'d>>> {
-    let factory = Factory::new();
-    let expression = factory
-        .build(expr)
-        .map_err(|e| PyValueError::new_err(format!("Invalid XPath {expr:?}: {e:?}")))?
-        .ok_or_else(|| PyValueError::new_err("Provided XPath expression is empty"))?;
+/// Elements rendered as paragraphs (surrounded by a blank line) by `to_text`
+/// and `to_markdown`.
+const PARAGRAPH_ELEMENTS: &[&str] = &[
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "blockquote",
+    "pre",
+    "table",
+    "hr",
+    "figure",
+];
 
-    let context = Context::new();
-    let result = expression
-        .evaluate(&context, node)
-        .map_err(|e| PyValueError::new_err(format!("Failed to evaluate XPath {expr:?}: {e:?}")))?;
+/// Elements rendered on their own lines by `to_text` and `to_markdown`.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "body",
+    "caption",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "footer",
+    "form",
+    "header",
+    "legend",
+    "main",
+    "nav",
+    "section",
+    "summary",
+    "tr",
+];
 
-    match result {
-        Value::Nodeset(nodeset) => Ok(nodeset.document_order().into_iter().collect()),
-        other => Err(PyValueError::new_err(format!(
-            "XPath {expr:?} must return a node set (got {other:?})"
-        ))),
+#[derive(Clone, Copy)]
+enum ListKind {
+    Unordered,
+    Ordered(usize),
+}
+
+/// Renderer behind `to_text` and `to_markdown`: block elements start new
+/// lines, paragraphs and headings are separated by blank lines, list items get
+/// `- ` / `1. ` markers indented by nesting level, inline whitespace collapses
+/// to single spaces and `<pre>` keeps its whitespace.
+///
+/// In Markdown mode headings, emphasis, links, images, code and block quotes
+/// also get their Markdown syntax and literal text is escaped; any other tag
+/// degrades to its text.
+#[derive(Default)]
+struct TextRenderer {
+    markdown: bool,
+    out: String,
+    /// Line breaks owed before the next piece of text (capped at 2).
+    pending_newlines: usize,
+    pending_space: bool,
+    /// Drop `pending_space` before the next text, e.g. right after `**`.
+    glue: bool,
+    line_start: bool,
+    lists: Vec<ListKind>,
+    /// List marker to print at the start of the next line.
+    marker: Option<String>,
+    /// `href` of each open `<a>`, to close `[text](href)`.
+    links: Vec<Option<String>>,
+    pre: usize,
+    code: usize,
+    quote: usize,
+    /// Quote depth of the line currently being written.
+    line_quote: usize,
+}
+
+impl TextRenderer {
+    fn render(el: ElementRef<'_>, markdown: bool) -> String {
+        let mut renderer = TextRenderer {
+            markdown,
+            line_start: true,
+            ..Default::default()
+        };
+        let mut skipping: Option<NodeId> = None;
+        for edge in el.traverse() {
+            match edge {
+                Edge::Open(node) if skipping.is_none() => match node.value() {
+                    Node::Text(text) => renderer.text(text),
+                    Node::Element(element)
+                        if node.id() != el.id()
+                            && (element.name() == "head"
+                                || is_non_content_element(element.name())) =>
+                    {
+                        skipping = Some(node.id());
+                    }
+                    Node::Element(element) => renderer.open(element),
+                    _ => {}
+                },
+                Edge::Close(node) if skipping == Some(node.id()) => skipping = None,
+                Edge::Close(node) if skipping.is_none() => {
+                    if let Some(element) = node.value().as_element() {
+                        renderer.close(element.name());
+                    }
+                }
+                _ => {}
+            }
+        }
+        renderer.out.truncate(renderer.out.trim_end().len());
+        renderer.out
+    }
+
+    fn open(&mut self, element: &scraper::node::Element) {
+        let name = element.name();
+        match name {
+            "br" => self.pending_newlines = (self.pending_newlines + 1).min(2),
+            "ul" | "ol" => {
+                if self.marker.is_some() {
+                    self.flush();
+                }
+                self.block(if self.lists.is_empty() { 2 } else { 1 });
+                self.lists.push(if name == "ol" {
+                    let start = element.attr("start").and_then(|s| s.trim().parse().ok());
+                    ListKind::Ordered(start.unwrap_or(1))
+                } else {
+                    ListKind::Unordered
+                });
+            }
+            "li" => {
+                self.block(1);
+                self.marker = Some(match self.lists.last_mut() {
+                    Some(ListKind::Ordered(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => "- ".to_string(),
+                });
+            }
+            "td" | "th" => self.pending_space = true,
+            "pre" => {
+                self.block(2);
+                if self.markdown {
+                    self.syntax("```", true);
+                    self.pending_newlines = 1;
+                }
+                self.pre += 1;
+            }
+            _ if !self.markdown => {
+                if PARAGRAPH_ELEMENTS.contains(&name) {
+                    self.block(2);
+                } else if BLOCK_ELEMENTS.contains(&name) {
+                    self.block(1);
+                }
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.block(2);
+                let level = usize::from(name.as_bytes()[1] - b'0');
+                self.syntax(&format!("{} ", "#".repeat(level)), true);
+            }
+            "blockquote" => {
+                self.block(2);
+                self.quote += 1;
+            }
+            "hr" => {
+                self.block(2);
+                self.syntax("---", true);
+                self.block(2);
+            }
+            "strong" | "b" => self.syntax("**", true),
+            "em" | "i" => self.syntax("*", true),
+            "code" if self.pre == 0 => {
+                self.syntax("`", true);
+                self.code += 1;
+            }
+            "a" => {
+                let href = element.attr("href").map(str::to_string);
+                if href.is_some() {
+                    self.syntax("[", true);
+                }
+                self.links.push(href);
+            }
+            "img" => {
+                if let Some(src) = element.attr("src") {
+                    let alt = escape_markdown(element.attr("alt").unwrap_or_default());
+                    self.syntax(&format!("![{alt}]({src})"), false);
+                }
+            }
+            _ if PARAGRAPH_ELEMENTS.contains(&name) => self.block(2),
+            _ if BLOCK_ELEMENTS.contains(&name) => self.block(1),
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        match name {
+            "ul" | "ol" => {
+                self.lists.pop();
+                self.block(if self.lists.is_empty() { 2 } else { 1 });
+            }
+            "li" => self.block(1),
+            "pre" => {
+                self.pre -= 1;
+                if self.markdown {
+                    self.pending_newlines = 1;
+                    self.syntax("```", false);
+                }
+                self.block(2);
+            }
+            _ if !self.markdown => {
+                if PARAGRAPH_ELEMENTS.contains(&name) {
+                    self.block(2);
+                } else if BLOCK_ELEMENTS.contains(&name) {
+                    self.block(1);
+                }
+            }
+            "blockquote" => {
+                self.quote -= 1;
+                self.block(2);
+            }
+            "strong" | "b" => self.closing_syntax("**"),
+            "em" | "i" => self.closing_syntax("*"),
+            "code" if self.pre == 0 => {
+                self.code -= 1;
+                self.closing_syntax("`");
+            }
+            "a" => {
+                if let Some(href) = self.links.pop().flatten() {
+                    self.closing_syntax(&format!("]({href})"));
+                }
+            }
+            _ if PARAGRAPH_ELEMENTS.contains(&name) => self.block(2),
+            _ if BLOCK_ELEMENTS.contains(&name) => self.block(1),
+            _ => {}
+        }
+    }
+
+    fn block(&mut self, newlines: usize) {
+        self.pending_newlines = self.pending_newlines.max(newlines);
+    }
+
+    /// Emit owed line breaks, then quote prefixes, indentation and any list marker.
+    fn flush(&mut self) {
+        if self.pending_newlines > 0 && !self.out.is_empty() {
+            self.out.truncate(self.out.trim_end_matches(' ').len());
+            let blank_quote = self.quote.min(self.line_quote);
+            for i in 0..self.pending_newlines {
+                if i > 0 {
+                    self.out.push_str(&">".repeat(blank_quote));
+                }
+                self.out.push('\n');
+            }
+            self.line_start = true;
+        }
+        self.pending_newlines = 0;
+        if self.line_start {
+            self.out.push_str(&"> ".repeat(self.quote));
+            self.line_quote = self.quote;
+            let depth = self.lists.len();
+            match self.marker.take() {
+                Some(marker) => {
+                    self.out.push_str(&"  ".repeat(depth.saturating_sub(1)));
+                    self.out.push_str(&marker);
+                }
+                None => self.out.push_str(&"  ".repeat(depth)),
+            }
+            self.line_start = false;
+            self.pending_space = false;
+        }
+    }
+
+    /// Write Markdown syntax as-is, separated from preceding text by any pending
+    /// space. With `glue`, whitespace right after it is dropped (`**bold**`).
+    fn syntax(&mut self, syntax: &str, glue: bool) {
+        let space = self.pending_space && self.pending_newlines == 0 && !self.line_start;
+        self.flush();
+        if space && !self.glue {
+            self.out.push(' ');
+        }
+        self.out.push_str(syntax);
+        self.pending_space = false;
+        self.glue = glue;
+    }
+
+    /// Write closing syntax directly after the preceding text, moving any
+    /// pending space after it.
+    fn closing_syntax(&mut self, syntax: &str) {
+        self.flush();
+        self.out.push_str(syntax);
+        self.glue = false;
+    }
+
+    fn text(&mut self, text: &str) {
+        for ch in text.chars() {
+            if self.pre > 0 {
+                if ch == '\n' {
+                    self.out.push('\n');
+                    self.line_start = true;
+                } else {
+                    self.flush();
+                    self.out.push(ch);
+                }
+            } else if ch.is_whitespace() {
+                self.pending_space = true;
+            } else {
+                let space = self.pending_space
+                    && self.pending_newlines == 0
+                    && !self.line_start
+                    && !self.glue;
+                self.flush();
+                if space {
+                    self.out.push(' ');
+                }
+                self.pending_space = false;
+                self.glue = false;
+                if self.markdown && self.code == 0 && is_markdown_special(ch) {
+                    self.out.push('\\');
+                }
+                self.out.push(ch);
+            }
+        }
     }
 }
 
-fn evaluate_xpath_elements<'d>(node: XPathNode<'d>, expr: &str) -> PyResult<Vec<Element>> {
-    evaluate_xpath_nodes(node, expr)?
-        .into_iter()
-        .map(snapshot_xpath_element)
+fn is_markdown_special(ch: char) -> bool {
+    matches!(ch, '\\' | '*' | '_' | '`' | '[' | ']')
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if is_markdown_special(ch) {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Upper bound for `colspan`, matching the HTML table processing model.
+const MAX_COLSPAN: usize = 1000;
+
+/// Rows of `table` as `(header, value)` pairs, keyed by the first row's cells.
+///
+/// Only rows that belong to `table` itself are read (directly or through
+/// `thead`/`tbody`/`tfoot`), so nested tables are left alone. Cells with a
+/// `colspan` repeat their text across the spanned columns, short rows are padded
+/// with empty strings, and cells past the last header are keyed by column index.
+fn table_records(table: ElementRef<'_>) -> Vec<Vec<(String, String)>> {
+    let mut rows = table_rows(table);
+
+    let Some(header_row) = rows.next() else {
+        return Vec::new();
+    };
+    let headers = table_headers(row_cells(header_row));
+
+    rows.map(|row| {
+        let mut cells = row_cells(row);
+        if cells.len() < headers.len() {
+            cells.resize(headers.len(), String::new());
+        }
+        cells
+            .into_iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                let header = headers.get(i).cloned().unwrap_or_else(|| i.to_string());
+                (header, cell)
+            })
+            .collect()
+    })
+    .collect()
+}
+
+/// The `tr` rows that belong to `table` itself, directly or through
+/// `thead`/`tbody`/`tfoot`, in document order.
+fn table_rows<'a>(table: ElementRef<'a>) -> impl Iterator<Item = ElementRef<'a>> {
+    table
+        .child_elements()
+        .flat_map(|child| match child.value().name() {
+            "tr" => vec![child],
+            "thead" | "tbody" | "tfoot" => child
+                .child_elements()
+                .filter(|row| row.value().name() == "tr")
+                .collect(),
+            _ => Vec::new(),
+        })
+}
+
+/// Upper bound for `rowspan`, matching the HTML table processing model.
+const MAX_ROWSPAN: usize = 65534;
+
+/// `table` as a grid of cell texts with `rowspan`/`colspan` expanded.
+///
+/// A spanned cell's text is copied into every slot it covers. `rowspan="0"`
+/// and spans running past the last row stop at the end of the table, and rows
+/// are padded with empty strings to the widest row.
+fn table_matrix(table: ElementRef<'_>) -> Vec<Vec<String>> {
+    let rows: Vec<ElementRef<'_>> = table_rows(table).collect();
+    let mut grid: Vec<Vec<Option<String>>> = vec![Vec::new(); rows.len()];
+    for (r, row) in rows.iter().enumerate() {
+        let mut col = 0;
+        for cell in row
+            .child_elements()
+            .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+        {
+            while grid[r].get(col).is_some_and(Option::is_some) {
+                col += 1;
+            }
+            let span = |name: &str| {
+                cell.attr(name)
+                    .and_then(|span| span.trim().parse::<usize>().ok())
+            };
+            let colspan = span("colspan").unwrap_or(1).clamp(1, MAX_COLSPAN);
+            let rowspan = match span("rowspan") {
+                Some(0) => rows.len() - r,
+                span => span.unwrap_or(1).clamp(1, MAX_ROWSPAN),
+            };
+            let text = element_text(cell);
+            for spanned in grid.iter_mut().skip(r).take(rowspan) {
+                if spanned.len() < col + colspan {
+                    spanned.resize(col + colspan, None);
+                }
+                for slot in &mut spanned[col..col + colspan] {
+                    *slot = Some(text.clone());
+                }
+            }
+            col += colspan;
+        }
+    }
+    let width = grid.iter().map(Vec::len).max().unwrap_or(0);
+    grid.into_iter()
+        .map(|row| {
+            let mut row: Vec<String> = row.into_iter().map(Option::unwrap_or_default).collect();
+            row.resize(width, String::new());
+            row
+        })
         .collect()
 }
 
-fn find_first_element_by_name<'d>(node: XPathNode<'d>, name: &str) -> Option<XPathNode<'d>> {
-    if let Some(element) = node.element()
-        && element.name().local_part() == name
-    {
-        return Some(node);
+/// Text of each `td`/`th` in `row`, repeated for cells with a `colspan`.
+fn row_cells(row: ElementRef<'_>) -> Vec<String> {
+    let mut cells = Vec::new();
+    for cell in row
+        .child_elements()
+        .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+    {
+        let span = cell
+            .attr("colspan")
+            .and_then(|span| span.trim().parse::<usize>().ok())
+            .unwrap_or(1)
+            .clamp(1, MAX_COLSPAN);
+        let text = element_text(cell);
+        cells.extend(std::iter::repeat_n(text, span));
+    }
+    cells
+}
+
+/// Make header names usable as dict keys: blank headers become their column
+/// index and repeats get a `.1`, `.2`, ... suffix (as pandas does).
+fn table_headers(cells: Vec<String>) -> Vec<String> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    cells
+        .into_iter()
+        .enumerate()
+        .map(|(i, cell)| {
+            let header = if cell.is_empty() { i.to_string() } else { cell };
+            let count = seen.entry(header.clone()).or_insert(0);
+            *count += 1;
+            if *count == 1 {
+                header
+            } else {
+                format!("{header}.{}", *count - 1)
+            }
+        })
+        .collect()
+}
+
+/// Convert table records to Python dicts, keeping the column order.
+fn records_to_py(py: Python<'_>, records: Vec<Vec<(String, String)>>) -> PyResult<Vec<Py<PyDict>>> {
+    records
+        .into_iter()
+        .map(|record| {
+            let dict = PyDict::new(py);
+            for (header, value) in record {
+                dict.set_item(header, value)?;
+            }
+            Ok(dict.into())
+        })
+        .collect()
+}
+
+/// A step of the pre-order walk behind `Document.to_tree`, copied out of the
+/// tree so the Python dicts can be built after the lock is released.
+enum TreeEvent {
+    Open(String, IndexMap<String, String>),
+    Text(String),
+    Comment(String),
+    Close,
+}
+
+/// A `<form>` read out of the tree: `action`, `method` and its `fields`.
+struct FormData {
+    action: Option<String>,
+    method: String,
+    fields: Vec<FormField>,
+}
+
+/// One form control with its default value; see `FormField::read`.
+struct FormField {
+    name: Option<String>,
+    control_type: String,
+    value: Option<String>,
+    checked: bool,
+    options: Vec<String>,
+}
+
+impl FormData {
+    fn read(form: ElementRef<'_>) -> Self {
+        let controls = Selector::parse("input, select, textarea, button").expect("static selector");
+        let method = form
+            .attr("method")
+            .map(|method| method.trim().to_ascii_lowercase())
+            .filter(|method| !method.is_empty())
+            .unwrap_or_else(|| "get".to_string());
+        Self {
+            action: form.attr("action").map(str::to_string),
+            method,
+            fields: form.select(&controls).map(FormField::read).collect(),
+        }
+    }
+
+    /// Describe the form as a dict: `action`, `method` and its `fields`.
+    fn into_py(self, py: Python<'_>) -> PyResult<Py<PyDict>> {
+        let fields = self
+            .fields
+            .into_iter()
+            .map(|field| field.into_py(py))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let dict = PyDict::new(py);
+        dict.set_item("action", self.action)?;
+        dict.set_item("method", self.method)?;
+        dict.set_item("fields", fields)?;
+        Ok(dict.into())
+    }
+}
+
+impl FormField {
+    /// Read one form control with its default value.
+    ///
+    /// `value` is what the control starts out with: the `value` attribute for
+    /// inputs and buttons ("on" for checkboxes and radios without one), the raw
+    /// contents of a `<textarea>`, and the selected (or first) option of a
+    /// `<select>`.
+    fn read(control: ElementRef<'_>) -> Self {
+        let tag = control.value().name();
+        let control_type = match tag {
+            "input" | "button" => control
+                .attr("type")
+                .map(|kind| kind.trim().to_ascii_lowercase())
+                .filter(|kind| !kind.is_empty())
+                .unwrap_or_else(|| if tag == "input" { "text" } else { "submit" }.to_string()),
+            "select" if control.attr("multiple").is_some() => "select-multiple".to_string(),
+            _ => tag.to_string(),
+        };
+
+        let mut options = Vec::new();
+        let value = match tag {
+            "textarea" => Some(control.text().collect::<String>()),
+            "select" => {
+                let option_selector = Selector::parse("option").expect("static selector");
+                let mut selected = None;
+                for option in control.select(&option_selector) {
+                    let value = option
+                        .attr("value")
+                        .map(str::to_string)
+                        .unwrap_or_else(|| element_text(option));
+                    if selected.is_none() && option.attr("selected").is_some() {
+                        selected = Some(value.clone());
+                    }
+                    options.push(value);
+                }
+                if selected.is_none() && control_type == "select" {
+                    selected = options.first().cloned();
+                }
+                selected
+            }
+            _ => Some(match control.attr("value") {
+                Some(value) => value.to_string(),
+                None if matches!(control_type.as_str(), "checkbox" | "radio") => "on".to_string(),
+                None => String::new(),
+            }),
+        };
+
+        Self {
+            name: control.attr("name").map(str::to_string),
+            control_type,
+            value,
+            checked: control.attr("checked").is_some(),
+            options,
+        }
+    }
+
+    fn into_py(self, py: Python<'_>) -> PyResult<Py<PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("name", self.name)?;
+        dict.set_item("type", self.control_type)?;
+        dict.set_item("value", self.value)?;
+        dict.set_item("checked", self.checked)?;
+        dict.set_item("options", self.options)?;
+        Ok(dict.into())
+    }
+}
+
+/// Convert a parsed JSON value into the equivalent Python object.
+fn json_to_py<'py>(py: Python<'py>, value: &serde_json::Value) -> PyResult<Bound<'py, PyAny>> {
+    use serde_json::Value as Json;
+
+    Ok(match value {
+        Json::Null => py.None().into_bound(py),
+        Json::Bool(flag) => PyBool::new(py, *flag).to_owned().into_any(),
+        Json::Number(number) => {
+            if let Some(int) = number.as_i64() {
+                int.into_pyobject(py)?.into_any()
+            } else if let Some(int) = number.as_u64() {
+                int.into_pyobject(py)?.into_any()
+            } else {
+                number
+                    .as_f64()
+                    .unwrap_or(f64::NAN)
+                    .into_pyobject(py)?
+                    .into_any()
+            }
+        }
+        Json::String(text) => PyString::new(py, text).into_any(),
+        Json::Array(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(json_to_py(py, item)?)?;
+            }
+            list.into_any()
+        }
+        Json::Object(map) => {
+            let dict = PyDict::new(py);
+            for (key, item) in map {
+                dict.set_item(key, json_to_py(py, item)?)?;
+            }
+            dict.into_any()
+        }
+    })
+}
+
+fn element_attrs(el: ElementRef<'_>) -> IndexMap<String, String> {
+    el.value()
+        .attrs()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+/// The node after `node` in document order (pre-order), descending first.
+fn next_in_order(node: NodeRef<'_, Node>) -> Option<NodeRef<'_, Node>> {
+    node.first_child().or_else(|| next_after_subtree(node))
+}
+
+/// The first node after `node` and all of its descendants in document order.
+fn next_after_subtree(node: NodeRef<'_, Node>) -> Option<NodeRef<'_, Node>> {
+    std::iter::once(node)
+        .chain(node.ancestors())
+        .find_map(|n| n.next_sibling())
+}
+
+/// The node before `node` in document order: the last descendant of the
+/// previous sibling, or else the parent.
+fn previous_in_order(node: NodeRef<'_, Node>) -> Option<NodeRef<'_, Node>> {
+    let Some(mut current) = node.prev_sibling() else {
+        return node.parent();
+    };
+    while let Some(child) = current.last_child() {
+        current = child;
+    }
+    Some(current)
+}
+
+/// A CSS selector path that matches exactly `el`, e.g.
+/// `html > body > div.content > ul > li:nth-child(3)`.
+///
+/// Each step is the tag plus its classes, with `:nth-child()` added when a
+/// sibling shares the tag. The walk stops early at an ancestor (or `el`) whose
+/// `id` is unique in the document, which then starts the path as `tag#id`.
+fn css_path(el: ElementRef<'_>) -> String {
+    let mut steps = Vec::new();
+    let chain = std::iter::once(el)
+        .chain(el.ancestors().filter_map(ElementRef::wrap))
+        .filter(|node| !is_fragment_wrapper(*node));
+    for node in chain {
+        let element = node.value();
+        let mut step = element.name().to_string();
+        if let Some(id) = element.id().filter(|id| is_unique_id(node, id)) {
+            step.push('#');
+            step.push_str(&escape_css_identifier(id));
+            steps.push(step);
+            break;
+        }
+        for class in element.classes() {
+            step.push('.');
+            step.push_str(&escape_css_identifier(class));
+        }
+        let siblings = node
+            .parent()
+            .into_iter()
+            .flat_map(|parent| parent.children())
+            .filter_map(ElementRef::wrap);
+        let mut position = 0;
+        let mut same_tag = 0;
+        for (index, sibling) in siblings.enumerate() {
+            if sibling.value().name() == element.name() {
+                same_tag += 1;
+            }
+            if sibling.id() == node.id() {
+                position = index + 1;
+            }
+        }
+        if same_tag > 1 {
+            step.push_str(&format!(":nth-child({position})"));
+        }
+        steps.push(step);
+    }
+    steps.reverse();
+    steps.join(" > ")
+}
+
+fn is_unique_id(el: ElementRef<'_>, id: &str) -> bool {
+    el.tree()
+        .root()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|other| other.value().id() == Some(id))
+        .take(2)
+        .count()
+        == 1
+}
+
+/// Escape a string for use as a CSS identifier, following the CSSOM `CSS.escape()` rules.
+fn escape_css_identifier(ident: &str) -> String {
+    let mut escaped = String::with_capacity(ident.len());
+    let first = ident.chars().next();
+    let only_dash = ident == "-";
+
+    for (i, ch) in ident.chars().enumerate() {
+        let leading_digit = ch.is_ascii_digit() && (i == 0 || (i == 1 && first == Some('-')));
+        match ch {
+            '\0' => escaped.push('\u{FFFD}'),
+            '\u{1}'..='\u{1F}' | '\u{7F}' => escaped.push_str(&format!("\\{:x} ", ch as u32)),
+            _ if leading_digit => escaped.push_str(&format!("\\{:x} ", ch as u32)),
+            '-' if only_dash => escaped.push_str("\\-"),
+            '-' | '_' | '0'..='9' | 'a'..='z' | 'A'..='Z' => escaped.push(ch),
+            _ if !ch.is_ascii() => escaped.push(ch),
+            _ => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+        }
+    }
+    escaped
+}
+
+create_exception!(
+    scraper_rs,
+    SelectorError,
+    PyValueError,
+    "Raised for CSS selectors that fail to parse. Subclass of ValueError."
+);
+
+create_exception!(
+    scraper_rs,
+    ParseError,
+    PyValueError,
+    "Raised by strict parsing for malformed HTML. Subclass of ValueError."
+);
+
+fn parse_selector(css: &str) -> PyResult<Selector> {
+    Selector::parse(css)
+        .map_err(|e| SelectorError::new_err(format!("Invalid CSS selector {css:?}: {e:?}")))
+}
+
+/// A pre-compiled CSS selector that can be reused across many queries.
+///
+/// Compiling once avoids re-parsing the selector string on every call:
+///
+///     sel = CssSelector("div.item")
+///     for doc in docs:
+///         items = doc.select_compiled(sel)
+#[pyclass(module = "scraper_rs", frozen)]
+pub struct CssSelector {
+    css: String,
+    selector: Selector,
+}
+
+#[pymethods]
+impl CssSelector {
+    /// Compile a CSS selector; raises ValueError on invalid syntax.
+    #[new]
+    pub fn new(css: &str) -> PyResult<Self> {
+        Ok(Self {
+            css: css.to_string(),
+            selector: parse_selector(css)?,
+        })
+    }
+
+    /// The selector string this object was compiled from.
+    #[getter]
+    pub fn css(&self) -> &str {
+        &self.css
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<CssSelector css={:?}>", self.css)
+    }
+}
+
+/// The text of the first element under `container` matching `selector`, or its
+/// `attr` attribute when one is named. None when nothing matches or the
+/// attribute is missing.
+fn scoped_value(
+    container: ElementRef<'_>,
+    selector: &Selector,
+    attr: Option<&str>,
+) -> Option<String> {
+    let found = container.select(selector).next()?;
+    match attr {
+        Some(name) => found.value().attr(name).map(str::to_string),
+        None => Some(element_text(found)),
+    }
+}
+
+/// What to read from each matched element: a `select_csv` column or a
+/// `select_unique` key.
+enum ElementField {
+    Text,
+    Html,
+    Tag,
+    Attr(String),
+}
+
+impl ElementField {
+    /// Parse `"text"`, `"html"`, `"tag"` or `"attr:NAME"`; `kind` names the
+    /// argument in the error message.
+    fn parse(spec: &str, kind: &str) -> PyResult<Self> {
+        match spec {
+            "text" => Ok(Self::Text),
+            "html" => Ok(Self::Html),
+            "tag" => Ok(Self::Tag),
+            _ => match spec.strip_prefix("attr:") {
+                Some(name) if !name.is_empty() => Ok(Self::Attr(name.to_string())),
+                _ => Err(PyValueError::new_err(format!(
+                    "Invalid {kind} {spec:?}: expected \"text\", \"html\", \"tag\" or \"attr:NAME\""
+                ))),
+            },
+        }
+    }
+
+    /// The field's value, or None for a missing attribute.
+    fn lookup(&self, el: ElementRef<'_>) -> Option<String> {
+        match self {
+            Self::Text => Some(element_text(el)),
+            Self::Html => Some(el.inner_html()),
+            Self::Tag => Some(el.value().name().to_string()),
+            Self::Attr(name) => el.value().attr(name).map(str::to_string),
+        }
+    }
+
+    fn value(&self, el: ElementRef<'_>) -> String {
+        self.lookup(el).unwrap_or_default()
+    }
+}
+
+/// The language of `el` as HTML scopes it: the nearest `lang` attribute on it
+/// or an ancestor, else the document's Content-Language pragma. An empty
+/// `lang` means unknown and stops the search.
+fn element_lang(el: ElementRef<'_>) -> Option<String> {
+    let declared = std::iter::once(el)
+        .chain(el.ancestors().filter_map(ElementRef::wrap))
+        .find_map(|el| el.value().attr("lang"));
+    match declared {
+        Some(lang) => Some(lang.trim())
+            .filter(|lang| !lang.is_empty())
+            .map(str::to_string),
+        None => content_language(el.tree().root()),
+    }
+}
+
+/// The first language of the first `<meta http-equiv="Content-Language">`
+/// under `root`, which HTML uses as the default when no `lang` is set.
+fn content_language(root: NodeRef<'_, Node>) -> Option<String> {
+    root.descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|el| el.value().name() == "meta")
+        .find(|el| {
+            el.attr("http-equiv")
+                .is_some_and(|value| value.trim().eq_ignore_ascii_case("content-language"))
+        })
+        .and_then(|el| el.attr("content"))
+        .and_then(|content| content.split(',').next())
+        .map(str::trim)
+        .filter(|lang| !lang.is_empty())
+        .map(str::to_string)
+}
+
+const EMAIL_PATTERN: &str =
+    r"(?i)\b[a-z0-9._%+-]+@(?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+[a-z]{2,}\b";
+
+/// A phone-shaped run: groups of up to four digits (optionally in
+/// parentheses) joined by single spaces, dots or dashes, after a non-word
+/// character so digits inside longer tokens do not match.
+const PHONE_PATTERN: &str =
+    r"(?:^|[^\w+])(\+?(?:\(\d{1,4}\)|\d{1,4})(?:[ .-]?(?:\(\d{1,4}\)|\d{1,4})){1,5})";
+
+/// Lowercased `value` if it is exactly one email address.
+fn normalize_email(value: &str, email: &Regex) -> Option<String> {
+    let value = value.trim();
+    email
+        .find(value)
+        .filter(|found| found.len() == value.len())
+        .map(|_| value.to_ascii_lowercase())
+}
+
+/// `value` reduced to its digits, with a leading `+` kept, when it has the
+/// 7 to 15 digits of a plausible (E.164-sized) phone number.
+fn normalize_phone(value: &str) -> Option<String> {
+    let value = value.trim();
+    let digits: String = value.chars().filter(char::is_ascii_digit).collect();
+    if !(7..=15).contains(&digits.len()) {
+        return None;
+    }
+    Some(if value.starts_with('+') {
+        format!("+{digits}")
+    } else {
+        digits
+    })
+}
+
+/// Whether `value` reads as a calendar date (`2024-01-31`, `31.01.2024`, ...)
+/// rather than a phone number.
+fn looks_like_date(value: &str) -> bool {
+    let groups: Vec<&str> = value.split(['-', '.', '/']).collect();
+    groups.len() == 3
+        && groups
+            .iter()
+            .all(|group| group.bytes().all(|b| b.is_ascii_digit()))
+        && (groups[0].len() == 4 || groups[2].len() == 4)
+        && groups.iter().all(|group| (1..=4).contains(&group.len()))
+}
+
+/// The value of every `<a href="SCHEME...">` under `html`, with the scheme
+/// (matched case-insensitively) and any `?query` removed.
+fn scheme_hrefs<'a>(html: &'a Html, scheme: &str) -> Vec<&'a str> {
+    let selector = Selector::parse("a[href]").expect("static selector");
+    select_elements(html, &selector)
+        .filter_map(|el| el.value().attr("href"))
+        .filter_map(|href| {
+            let href = href.trim();
+            let prefix = href.get(..scheme.len())?;
+            if !prefix.eq_ignore_ascii_case(scheme) {
+                return None;
+            }
+            let value = &href[scheme.len()..];
+            Some(value.split('?').next().unwrap_or_default())
+        })
+        .collect()
+}
+
+/// Whether `el` is the `<html>` wrapper that `Html::parse_fragment` puts around
+/// the parsed nodes (the one element hanging directly off a fragment root).
+fn is_fragment_wrapper(el: ElementRef<'_>) -> bool {
+    el.parent()
+        .is_some_and(|parent| parent.parent().is_none() && parent.value().is_fragment())
+}
+
+/// Whether `attr` is the un-namespaced attribute `name`, the one
+/// `scraper`'s `Element::attr(name)` looks up.
+fn is_plain_attr(attr: &Attribute, name: &str) -> bool {
+    attr.name.ns == ns!() && &*attr.name.local == name
+}
+
+/// Rebuild the element at `node` with its attributes passed through `f`.
+///
+/// `scraper` caches an element's id and classes on first use, so the element
+/// is recreated rather than patched in place to keep selectors matching the
+/// new values.
+fn rewrite_attrs(mut node: NodeMut<'_, Node>, f: impl FnOnce(&mut Vec<Attribute>)) {
+    let Node::Element(element) = node.value() else {
+        unreachable!("element handle points at a non-element node");
+    };
+    let mut attrs: Vec<Attribute> = element
+        .attrs
+        .iter()
+        .map(|(name, value)| Attribute {
+            name: name.clone(),
+            value: StrTendril::from(&**value),
+        })
+        .collect();
+    f(&mut attrs);
+    *element = scraper::node::Element::new(element.name.clone(), attrs);
+}
+
+/// Whether `node` no longer hangs off the tree root because it, or one of its
+/// ancestors, was detached with `Element.remove`.
+fn is_detached(node: NodeRef<'_, Node>) -> bool {
+    let top = node.ancestors().last().unwrap_or(node);
+    !(top.value().is_document() || top.value().is_fragment())
+}
+
+/// Whether `node` sits inside the content fragment of a `<template>`.
+fn in_template_content(node: NodeRef<'_, Node>) -> bool {
+    node.ancestors().any(|ancestor| {
+        ancestor.value().is_fragment()
+            && ancestor
+                .parent()
+                .and_then(ElementRef::wrap)
+                .is_some_and(|template| template.value().name() == "template")
+    })
+}
+
+/// `Html::select`, minus the synthetic wrapper of fragment trees and removed
+/// nodes (`Html::select` scans every node in the arena, attached or not).
+fn select_elements<'a, 'b>(
+    html: &'a Html,
+    selector: &'b Selector,
+) -> impl Iterator<Item = ElementRef<'a>> + use<'a, 'b> {
+    html.select(selector)
+        .filter(|el| !is_fragment_wrapper(*el) && !is_detached(**el))
+}
+
+fn select_fragment_with(
+    html: &str,
+    selector: &Selector,
+    base_url: Option<Url>,
+    limit: usize,
+) -> Vec<Element> {
+    let tree = DocumentTree::new(Html::parse_fragment(html), base_url);
+    let fragment = tree.lock();
+    select_elements(&fragment, selector)
+        .take(limit)
+        .map(|el| Element::from_ref(&tree, el))
+        .collect()
+}
+
+fn parse_base_url(base_url: &str) -> PyResult<Url> {
+    Url::parse(base_url)
+        .map_err(|e| PyValueError::new_err(format!("Invalid base_url {base_url:?}: {e}")))
+}
+
+/// The document base URL: the first `<base href>` resolved against `base_url`,
+/// falling back to `base_url` when there is no usable `<base>` element.
+fn document_base_url(html: &Html, base_url: Option<Url>) -> Option<Url> {
+    let selector = Selector::parse("base[href]").expect("static selector");
+    let href = select_elements(html, &selector)
+        .next()
+        .and_then(|el| el.value().attr("href"));
+    let Some(href) = href else {
+        return base_url;
+    };
+    match &base_url {
+        Some(base) => base.join(href).ok().or(base_url),
+        None => Url::parse(href).ok(),
+    }
+}
+
+/// Join `value` against `base`; without a base only absolute URLs resolve.
+fn resolve_url(base: Option<&Url>, value: &str) -> Option<String> {
+    let url = match base {
+        Some(base) => base.join(value),
+        None => Url::parse(value),
+    };
+    url.ok().map(String::from)
+}
+
+/// Attributes `extract_urls` reads a single URL from.
+const URL_ATTRIBUTES: &[&str] = &[
+    "href",
+    "src",
+    "data-src",
+    "poster",
+    "action",
+    "formaction",
+    "cite",
+];
+
+/// Attributes `extract_urls` reads a comma-separated candidate list from.
+const SRCSET_ATTRIBUTES: &[&str] = &["srcset", "data-srcset"];
+
+/// Candidate URLs of a `srcset` value, without their width/density descriptors.
+///
+/// Follows the HTML parsing rules closely enough for real pages: a URL is a run
+/// of non-whitespace, and a trailing comma ends the candidate early.
+fn srcset_urls(value: &str) -> Vec<&str> {
+    let mut urls = Vec::new();
+    let mut rest = value;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if rest.is_empty() {
+            return urls;
+        }
+        let end = rest
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let (url, tail) = rest.split_at(end);
+        if let Some(url) = url.strip_suffix(',') {
+            urls.push(url.trim_end_matches(','));
+            rest = tail;
+        } else {
+            urls.push(url);
+            rest = tail.find(',').map_or("", |i| &tail[i + 1..]);
+        }
+    }
+}
+
+/// Give XPath snapshots the base URL of the document they were taken from.
+fn with_base_url(mut elements: Vec<Element>, base_url: Option<&Url>) -> Vec<Element> {
+    if base_url.is_some() {
+        for element in &mut elements {
+            if let ElementSource::Snapshot(snapshot) = &mut element.source {
+                snapshot.base_url = base_url.cloned();
+            }
+        }
+    }
+    elements
+}
+
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::new();
+    for ch in value.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// HTML elements that never have children and are serialized without a closing tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Elements `sanitize` removes together with their contents unless they are
+/// explicitly allowed.
+const UNSAFE_ELEMENTS: &[&str] = &[
+    "script", "style", "iframe", "frame", "frameset", "object", "embed", "noscript", "template",
+    "base", "meta", "form",
+];
+
+/// Attributes whose value is loaded or followed as a URL.
+const URL_ATTRS: &[&str] = &[
+    "href",
+    "src",
+    "srcset",
+    "action",
+    "formaction",
+    "data",
+    "poster",
+    "background",
+    "cite",
+    "longdesc",
+    "lowsrc",
+    "dynsrc",
+    "manifest",
+    "icon",
+    "ping",
+    "xlink:href",
+];
+
+/// Whether an attribute can run script: `on*` event handlers, values using
+/// the `javascript:` or `vbscript:` scheme, and `data:` URLs in URL
+/// attributes (which can carry a whole HTML document).
+fn is_unsafe_attr(name: &str, value: &str) -> bool {
+    if name.starts_with("on") {
+        return true;
+    }
+    let scheme: String = value
+        .chars()
+        .filter(|ch| !ch.is_ascii_whitespace() && !ch.is_ascii_control())
+        .take(11)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    scheme.starts_with("javascript:")
+        || scheme.starts_with("vbscript:")
+        || (scheme.starts_with("data:") && URL_ATTRS.contains(&name))
+}
+
+/// Serialize the children of `container`, keeping only safe markup.
+///
+/// Unsafe elements and comments are dropped with their contents. With
+/// `allowed_tags`, any other element outside the list is unwrapped (its
+/// children are kept); with `allowed_attrs`, only those attribute names survive.
+/// `style` is dropped unless `allowed_attrs` names it, since CSS can load URLs.
+fn sanitize_children(
+    container: ElementRef<'_>,
+    allowed_tags: Option<&[String]>,
+    allowed_attrs: Option<&[String]>,
+) -> String {
+    let allowed = |list: Option<&[String]>, name: &str| {
+        list.is_none_or(|list| list.iter().any(|item| item.eq_ignore_ascii_case(name)))
+    };
+    let mut buf = String::new();
+    let mut skipping: Option<NodeId> = None;
+    for edge in container.traverse() {
+        match edge {
+            Edge::Open(node) if node.id() == container.id() => {}
+            Edge::Open(node) if skipping.is_none() => match node.value() {
+                Node::Text(text) => buf.push_str(&escape_html(text)),
+                Node::Element(element) => {
+                    let name = element.name();
+                    let explicitly_allowed = allowed_tags.is_some() && allowed(allowed_tags, name);
+                    if UNSAFE_ELEMENTS.contains(&name) && !explicitly_allowed {
+                        skipping = Some(node.id());
+                    } else if allowed(allowed_tags, name) {
+                        buf.push('<');
+                        buf.push_str(name);
+                        for (attr, value) in element.attrs() {
+                            let kept = if attr == "style" {
+                                allowed_attrs.is_some() && allowed(allowed_attrs, attr)
+                            } else {
+                                allowed(allowed_attrs, attr)
+                            };
+                            if kept && !is_unsafe_attr(attr, value) {
+                                buf.push(' ');
+                                buf.push_str(attr);
+                                buf.push_str("=\"");
+                                buf.push_str(&escape_html(value));
+                                buf.push('"');
+                            }
+                        }
+                        buf.push('>');
+                    }
+                }
+                _ => {}
+            },
+            Edge::Close(node) if skipping == Some(node.id()) => skipping = None,
+            Edge::Close(node) if skipping.is_none() && node.id() != container.id() => {
+                if let Some(element) = node.value().as_element() {
+                    let name = element.name();
+                    if allowed(allowed_tags, name) && !VOID_ELEMENTS.contains(&name) {
+                        buf.push_str("</");
+                        buf.push_str(name);
+                        buf.push('>');
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    buf
+}
+
+/// Escape text content for HTML output (attribute quotes are left alone).
+fn escape_html_text(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Serialize the tree under `root` with one tag, text node or comment per line,
+/// indented by `indent` spaces per nesting level, like BeautifulSoup's
+/// `prettify()`.
+///
+/// Text nodes are trimmed and whitespace-only ones dropped. `<pre>` and
+/// `<textarea>` are written unchanged on a single line because their
+/// whitespace is content.
+fn prettify_tree(root: NodeRef<'_, Node>, indent: usize) -> String {
+    let mut buf = String::new();
+    let mut line = |depth: usize, content: &str| {
+        buf.push_str(&" ".repeat(depth * indent));
+        buf.push_str(content);
+        buf.push('\n');
+    };
+    let mut depth = 0;
+    let mut skipping: Option<NodeId> = None;
+    for edge in root.traverse() {
+        match edge {
+            Edge::Open(node) if skipping.is_none() => match node.value() {
+                Node::Doctype(doctype) => line(depth, &format!("<!DOCTYPE {}>", doctype.name())),
+                Node::Comment(comment) => line(depth, &format!("<!--{}-->", &**comment)),
+                Node::Text(text) => {
+                    let text = text.trim();
+                    if text.is_empty() {
+                        continue;
+                    }
+                    let raw = node
+                        .parent()
+                        .and_then(|parent| parent.value().as_element())
+                        .is_some_and(|parent| matches!(parent.name(), "script" | "style"));
+                    if raw {
+                        line(depth, text);
+                    } else {
+                        line(depth, &escape_html_text(text));
+                    }
+                }
+                Node::Element(element) => {
+                    let el = ElementRef::wrap(node).expect("element node");
+                    if is_fragment_wrapper(el) {
+                        continue;
+                    }
+                    if matches!(element.name(), "pre" | "textarea") {
+                        line(depth, &el.html());
+                        skipping = Some(node.id());
+                        continue;
+                    }
+                    line(depth, &start_tag(element));
+                    if !VOID_ELEMENTS.contains(&element.name()) {
+                        depth += 1;
+                    }
+                }
+                _ => {}
+            },
+            Edge::Close(node) if skipping == Some(node.id()) => skipping = None,
+            Edge::Close(node) if skipping.is_none() => {
+                let Some(el) = ElementRef::wrap(node) else {
+                    continue;
+                };
+                let name = el.value().name();
+                if !is_fragment_wrapper(el) && !VOID_ELEMENTS.contains(&name) {
+                    depth -= 1;
+                    line(depth, &format!("</{name}>"));
+                }
+            }
+            _ => {}
+        }
+    }
+    buf
+}
+
+/// `<name attr="value" ...>` for `element`, with attribute values escaped.
+fn start_tag(element: &scraper::node::Element) -> String {
+    let mut tag = format!("<{}", element.name());
+    for (name, value) in element.attrs() {
+        tag.push_str(&format!(" {name}=\"{}\"", escape_html(value)));
+    }
+    tag.push('>');
+    tag
+}
+
+/// Elements next to which whitespace-only text is dropped by `minify`, on top
+/// of `PARAGRAPH_ELEMENTS` and `BLOCK_ELEMENTS`.
+const MINIFY_BOUNDARY_ELEMENTS: &[&str] = &[
+    "html", "head", "title", "meta", "link", "base", "script", "style", "ul", "ol", "li", "thead",
+    "tbody", "tfoot", "td", "th", "colgroup", "col", "select", "option", "optgroup",
+];
+
+/// Whether whitespace followed by `siblings` is insignificant for `minify`:
+/// the nearest sibling that is not a comment or whitespace-only text is a
+/// block-level element, or there is none.
+fn is_minify_boundary<'a>(mut siblings: impl Iterator<Item = NodeRef<'a, Node>>) -> bool {
+    let neighbour = siblings.find(|node| match node.value() {
+        Node::Comment(_) => false,
+        Node::Text(text) => !text.trim_ascii().is_empty(),
+        _ => true,
+    });
+    neighbour.is_none_or(|node| match node.value() {
+        Node::Element(element) => {
+            let name = element.name();
+            PARAGRAPH_ELEMENTS.contains(&name)
+                || BLOCK_ELEMENTS.contains(&name)
+                || MINIFY_BOUNDARY_ELEMENTS.contains(&name)
+        }
+        Node::Doctype(_) => true,
+        _ => false,
+    })
+}
+
+/// Serialize the tree under `root` without insignificant whitespace.
+///
+/// Whitespace-only text between block-level boundaries is dropped and other
+/// runs of HTML whitespace collapse to one space. `<pre>`, `<textarea>`,
+/// `<script>` and `<style>` contents and comments are kept as written.
+fn minify_tree(root: NodeRef<'_, Node>) -> String {
+    let mut buf = String::new();
+    let mut skipping: Option<NodeId> = None;
+    for edge in root.traverse() {
+        match edge {
+            Edge::Open(node) if skipping.is_none() => match node.value() {
+                Node::Doctype(doctype) => {
+                    buf.push_str(&format!("<!DOCTYPE {}>", doctype.name()));
+                }
+                Node::Comment(comment) => buf.push_str(&format!("<!--{}-->", &**comment)),
+                Node::Text(text) => {
+                    let raw = node
+                        .parent()
+                        .and_then(|parent| parent.value().as_element())
+                        .is_some_and(|parent| matches!(parent.name(), "script" | "style"));
+                    if raw {
+                        buf.push_str(text);
+                        continue;
+                    }
+                    // Only HTML whitespace collapses; NBSP is content here.
+                    let collapsed = collapse_whitespace(text, |ch| ch.is_ascii_whitespace());
+                    if collapsed == " "
+                        && is_minify_boundary(node.prev_siblings())
+                        && is_minify_boundary(node.next_siblings())
+                    {
+                        continue;
+                    }
+                    buf.push_str(&escape_html_text(&collapsed));
+                }
+                Node::Element(element) => {
+                    let el = ElementRef::wrap(node).expect("element node");
+                    if is_fragment_wrapper(el) {
+                        continue;
+                    }
+                    if matches!(element.name(), "pre" | "textarea") {
+                        buf.push_str(&el.html());
+                        skipping = Some(node.id());
+                        continue;
+                    }
+                    buf.push_str(&start_tag(element));
+                }
+                _ => {}
+            },
+            Edge::Close(node) if skipping == Some(node.id()) => skipping = None,
+            Edge::Close(node) if skipping.is_none() => {
+                let Some(el) = ElementRef::wrap(node) else {
+                    continue;
+                };
+                let name = el.value().name();
+                if !is_fragment_wrapper(el) && !VOID_ELEMENTS.contains(&name) {
+                    buf.push_str(&format!("</{name}>"));
+                }
+            }
+            _ => {}
+        }
+    }
+    buf
+}
+
+fn serialize_node_into(buf: &mut String, node: XPathNode<'_>) {
+    if let Some(element) = node.element() {
+        let name = element.name().local_part();
+        buf.push('<');
+        buf.push_str(name);
+
+        for attr in element.attributes().iter() {
+            buf.push(' ');
+            buf.push_str(attr.name().local_part());
+            buf.push('=');
+            buf.push('"');
+            buf.push_str(&escape_html(attr.value()));
+            buf.push('"');
+        }
+
+        buf.push('>');
+        if VOID_ELEMENTS.contains(&name) {
+            return;
+        }
+        for child in element.children() {
+            serialize_node_into(buf, child.into());
+        }
+        buf.push_str("</");
+        buf.push_str(name);
+        buf.push('>');
+    } else if let Some(text) = node.text() {
+        buf.push_str(&escape_html(text.text()));
+    }
+}
+
+fn serialize_children(node: XPathNode<'_>) -> String {
+    let mut buf = String::new();
+    for child in node.children() {
+        serialize_node_into(&mut buf, child);
+    }
+    buf
+}
+
+fn collect_text_nodes(node: XPathNode<'_>, out: &mut Vec<String>) {
+    if let Some(text) = node.text() {
+        out.push(text.text().to_string());
+    }
+
+    for child in node.children() {
+        if child
+            .element()
+            .is_some_and(|element| is_non_content_element(element.name().local_part()))
+        {
+            continue;
+        }
+        collect_text_nodes(child, out);
+    }
+}
+
+fn text_from_node(node: XPathNode<'_>) -> String {
+    let mut parts = Vec::new();
+    collect_text_nodes(node, &mut parts);
+    normalize_whitespace(&parts.join(" "))
+}
+
+fn snapshot_xpath_element(node: XPathNode<'_>) -> PyResult<Element> {
+    let element = node.element().ok_or_else(|| {
+        PyValueError::new_err("XPath expression must return element nodes for conversion")
+    })?;
+
+    let tag = element.name().local_part().to_string();
+    let text = text_from_node(node);
+    let inner_html = serialize_children(node);
+    let mut outer_html = String::new();
+    serialize_node_into(&mut outer_html, node);
+
+    let mut attrs = IndexMap::new();
+    for attr in element.attributes().iter() {
+        attrs.insert(
+            attr.name().local_part().to_string(),
+            attr.value().to_string(),
+        );
+    }
+
+    Ok(Element {
+        source: ElementSource::Snapshot(Box::new(ElementSnapshot {
+            tag,
+            text,
+            inner_html,
+            outer_html,
+            attrs,
+            base_url: None,
+        })),
+        hash: OnceLock::new(),
+    })
+}
+
+fn evaluate_xpath_nodes<'d>(node: XPathNode<'d>, expr: &str) -> PyResult<Vec<XPathNode<'d>>> {
+    let factory = Factory::new();
+    let expression = factory
+        .build(expr)
+        .map_err(|e| PyValueError::new_err(format!("Invalid XPath {expr:?}: {e:?}")))?
+        .ok_or_else(|| PyValueError::new_err("Provided XPath expression is empty"))?;
+
+    let context = Context::new();
+    let result = expression
+        .evaluate(&context, node)
+        .map_err(|e| PyValueError::new_err(format!("Failed to evaluate XPath {expr:?}: {e:?}")))?;
+
+    match result {
+        Value::Nodeset(nodeset) => Ok(nodeset.document_order().into_iter().collect()),
+        other => Err(PyValueError::new_err(format!(
+            "XPath {expr:?} must return a node set (got {other:?})"
+        ))),
+    }
+}
+
+/// Snapshots of at most `limit` nodes matched by `expr`, in document order.
+///
+/// Snapshotting serializes each node, so the `*_first` variants pass 1 to
+/// skip that work for the matches they would discard.
+fn evaluate_xpath_elements<'d>(
+    node: XPathNode<'d>,
+    expr: &str,
+    limit: usize,
+) -> PyResult<Vec<Element>> {
+    evaluate_xpath_nodes(node, expr)?
+        .into_iter()
+        .take(limit)
+        .map(snapshot_xpath_element)
+        .collect()
+}
+
+fn find_first_element_by_name<'d>(node: XPathNode<'d>, name: &str) -> Option<XPathNode<'d>> {
+    if let Some(element) = node.element()
+        && element.name().local_part() == name
+    {
+        return Some(node);
+    }
+
+    for child in node.children() {
+        if let Some(found) = find_first_element_by_name(child, name) {
+            return Some(found);
+        }
+    }
+
+    None
+}
+
+fn evaluate_fragment_xpath(html: &str, expr: &str, limit: usize) -> PyResult<Vec<Element>> {
+    let wrapped = format!("<xpath-fragment>{}</xpath-fragment>", html);
+    let package = sxd_html::parse_html(&wrapped);
+    let document = package.as_document();
+
+    let Some(wrapper) = find_first_element_by_name(document.root().into(), "xpath-fragment") else {
+        return Err(PyValueError::new_err(
+            "Failed to parse HTML fragment for XPath evaluation",
+        ));
+    };
+
+    evaluate_xpath_elements(wrapper, expr, limit)
+}
+
+/// All elements in `tree` matching `selector`, as node-backed Elements.
+fn select_in_tree(tree: &Arc<DocumentTree>, selector: &Selector) -> Vec<Element> {
+    let html = tree.lock();
+    select_elements(&html, selector)
+        .map(|el| Element::from_ref(tree, el))
+        .collect()
+}
+
+/// Lazy iterator over the matches of a selector, returned by
+/// `Document.iter_select`.
+///
+/// Each `next()` resumes the document-order walk where the previous match was
+/// found, so breaking out early skips the rest of the search and only the
+/// yielded elements are ever created.
+#[pyclass(module = "scraper_rs")]
+pub struct SelectIter {
+    tree: Arc<DocumentTree>,
+    selector: Selector,
+    /// Last node visited; None once the walk is exhausted.
+    position: Option<NodeId>,
+    /// The node following `position`'s subtree, where the walk resumes if
+    /// `position` was removed from the tree since it was yielded.
+    resume_after_removal: Option<NodeId>,
+}
+
+#[pymethods]
+impl SelectIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> Option<Element> {
+        let position = self.position?;
+        let html = self.tree.lock();
+        let last = html.tree.get(position)?;
+        let mut node = if is_detached(last) {
+            self.resume_after_removal
+                .and_then(|id| html.tree.get(id))
+                .filter(|next| !is_detached(*next))
+        } else {
+            next_in_order(last)
+        };
+        while let Some(current) = node {
+            if let Some(el) = ElementRef::wrap(current)
+                && !is_fragment_wrapper(el)
+                && self.selector.matches(&el)
+            {
+                self.position = Some(current.id());
+                self.resume_after_removal = next_after_subtree(current).map(|next| next.id());
+                return Some(Element::from_ref(&self.tree, el));
+            }
+            node = next_in_order(current);
+        }
+        self.position = None;
+        None
+    }
+}
+
+/// Lazy iterator over `(parent_tag, text)` for each text node, returned by
+/// `Document.iter_text`.
+///
+/// Like `SelectIter`, each `next()` resumes the document-order walk after the
+/// last text node it yielded.
+#[pyclass(module = "scraper_rs")]
+pub struct TextIter {
+    tree: Arc<DocumentTree>,
+    /// Last node visited; None once the walk is exhausted.
+    position: Option<NodeId>,
+    strip: bool,
+    include_scripts: bool,
+}
+
+#[pymethods]
+impl TextIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> Option<(String, String)> {
+        let position = self.position.take()?;
+        let html = self.tree.lock();
+        let last = html.tree.get(position).filter(|last| !is_detached(*last))?;
+        let mut node = next_in_order(last);
+        while let Some(current) = node {
+            match current.value() {
+                Node::Element(element)
+                    if !self.include_scripts && is_non_content_element(element.name()) =>
+                {
+                    node = next_after_subtree(current);
+                    continue;
+                }
+                Node::Text(text) => {
+                    let text = if self.strip { text.trim() } else { text };
+                    if !text.is_empty() {
+                        let tag = current
+                            .ancestors()
+                            .find_map(|ancestor| ancestor.value().as_element())
+                            .map_or("", |parent| parent.name());
+                        self.position = Some(current.id());
+                        return Some((tag.to_string(), text.to_string()));
+                    }
+                }
+                _ => {}
+            }
+            node = next_in_order(current);
+        }
+        None
+    }
+}
+
+/// Which parser builds a document's tree.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ParseMode {
+    /// A full HTML document; html5ever adds `<html>`, `<head>` and `<body>`.
+    Document,
+    /// An HTML snippet parsed in a `<body>` context.
+    Fragment,
+    /// XML (or XHTML) parsed with xml5ever: names keep their case and no HTML
+    /// structure is implied.
+    Xml,
+}
+
+impl ParseMode {
+    fn html(fragment: bool) -> Self {
+        if fragment {
+            Self::Fragment
+        } else {
+            Self::Document
+        }
+    }
+
+    fn parse(self, text: &str) -> Html {
+        match self {
+            Self::Document => Html::parse_document(text),
+            Self::Fragment => Html::parse_fragment(text),
+            Self::Xml => {
+                let sink = HtmlTreeSink::new(Html::new_document());
+                xml5ever::driver::parse_document(sink, Default::default()).one(text)
+            }
+        }
+    }
+}
+
+/// `HtmlTreeSink` plus the source line of every parse error, which scraper's
+/// sink does not keep.
+struct LineTrackingSink {
+    inner: HtmlTreeSink,
+    line: Cell<u64>,
+    error_lines: RefCell<Vec<u64>>,
+}
+
+impl TreeSink for LineTrackingSink {
+    type Output = (Html, Vec<u64>);
+    type Handle = NodeId;
+    type ElemName<'a> = <HtmlTreeSink as TreeSink>::ElemName<'a>;
+
+    fn finish(self) -> Self::Output {
+        (self.inner.finish(), self.error_lines.into_inner())
+    }
+
+    fn parse_error(&self, msg: Cow<'static, str>) {
+        self.error_lines.borrow_mut().push(self.line.get());
+        self.inner.parse_error(msg);
+    }
+
+    fn set_current_line(&self, line_number: u64) {
+        self.line.set(line_number);
+    }
+
+    fn get_document(&self) -> NodeId {
+        self.inner.get_document()
+    }
+
+    fn elem_name<'a>(&'a self, target: &'a NodeId) -> Self::ElemName<'a> {
+        self.inner.elem_name(target)
+    }
+
+    fn create_element(&self, name: QualName, attrs: Vec<Attribute>, flags: ElementFlags) -> NodeId {
+        self.inner.create_element(name, attrs, flags)
+    }
+
+    fn create_comment(&self, text: StrTendril) -> NodeId {
+        self.inner.create_comment(text)
+    }
+
+    fn create_pi(&self, target: StrTendril, data: StrTendril) -> NodeId {
+        self.inner.create_pi(target, data)
+    }
+
+    fn append(&self, parent: &NodeId, child: NodeOrText<NodeId>) {
+        self.inner.append(parent, child);
+    }
+
+    fn append_based_on_parent_node(
+        &self,
+        element: &NodeId,
+        prev_element: &NodeId,
+        child: NodeOrText<NodeId>,
+    ) {
+        self.inner
+            .append_based_on_parent_node(element, prev_element, child);
+    }
+
+    fn append_doctype_to_document(
+        &self,
+        name: StrTendril,
+        public_id: StrTendril,
+        system_id: StrTendril,
+    ) {
+        self.inner
+            .append_doctype_to_document(name, public_id, system_id);
+    }
+
+    fn get_template_contents(&self, target: &NodeId) -> NodeId {
+        self.inner.get_template_contents(target)
+    }
+
+    fn same_node(&self, x: &NodeId, y: &NodeId) -> bool {
+        self.inner.same_node(x, y)
+    }
+
+    fn set_quirks_mode(&self, mode: QuirksMode) {
+        self.inner.set_quirks_mode(mode);
+    }
+
+    fn append_before_sibling(&self, sibling: &NodeId, new_node: NodeOrText<NodeId>) {
+        self.inner.append_before_sibling(sibling, new_node);
+    }
+
+    fn add_attrs_if_missing(&self, target: &NodeId, attrs: Vec<Attribute>) {
+        self.inner.add_attrs_if_missing(target, attrs);
+    }
+
+    fn remove_from_parent(&self, target: &NodeId) {
+        self.inner.remove_from_parent(target);
+    }
+
+    fn reparent_children(&self, node: &NodeId, new_parent: &NodeId) {
+        self.inner.reparent_children(node, new_parent);
+    }
+}
+
+/// `ParseError` for the first problem html5ever reports in `text`, or None
+/// when the document is well-formed.
+///
+/// Only called once `Html::errors` is known to be non-empty, so the extra
+/// parse to recover line numbers is paid on the failure path alone.
+fn strict_parse_error(html: &Html, text: &str) -> Option<PyErr> {
+    let first = html.errors.first()?;
+    let sink = LineTrackingSink {
+        inner: HtmlTreeSink::new(Html::new_document()),
+        line: Cell::new(1),
+        error_lines: RefCell::new(Vec::new()),
+    };
+    let (_, lines) = html5ever::driver::parse_document(sink, Default::default()).one(text);
+    let line = lines.first().copied().unwrap_or(1);
+    let more = match html.errors.len() - 1 {
+        0 => String::new(),
+        1 => " (and 1 more error)".to_string(),
+        n => format!(" (and {n} more errors)"),
+    };
+    Some(ParseError::new_err(format!(
+        "HTML parse error at line {line}: {first}{more}"
+    )))
+}
+
+/// HTML that has been size-checked and parsed, but not yet wrapped in a `Document`.
+///
+/// Everything here is `Send`, so parsing can run without the GIL and on worker
+/// threads; only `Document::from_parsed` has to happen on the Python thread.
+struct ParsedHtml {
+    raw_html: String,
+    html: Html,
+    base_url: Option<Url>,
+    /// The `base_url` option as given, before any `<base href>` is applied.
+    base_url_option: Option<Url>,
+    mode: ParseMode,
+}
+
+impl ParsedHtml {
+    fn parse(
+        html: &str,
+        max_size_bytes: Option<usize>,
+        truncate_on_limit: bool,
+        base_url: Option<Url>,
+        mode: ParseMode,
+    ) -> PyResult<Self> {
+        let max_size_bytes = effective_max_size(max_size_bytes);
+        let html_to_parse = ensure_within_size_limit(html, max_size_bytes, truncate_on_limit)?;
+
+        // Parse using the Cow reference, then convert to owned String
+        let parsed = mode.parse(html_to_parse.as_ref());
+        let resolved = document_base_url(&parsed, base_url.clone());
+
+        Ok(Self {
+            raw_html: html_to_parse.into_owned(),
+            html: parsed,
+            base_url: resolved,
+            base_url_option: base_url,
+            mode,
+        })
+    }
+}
+
+/// Pickled form of a Document: raw HTML, base URL, fragment mode and XML mode.
+type DocumentState = (String, Option<String>, bool, bool);
+
+/// A parsed HTML document with convenient, Pythonic selectors.
+///
+/// Example:
+///
+///     from scraper_rs import Document
+///
+///     doc = Document("<html><body><a href='/x'>link</a></body></html>")
+///     a = doc.find("a")
+///     print(a.text, a.attr("href"))
+#[pyclass(module = "scraper_rs", unsendable)]
+pub struct Document {
+    raw_html: String,
+    /// The parsed tree; `None` once the document has been closed.
+    tree: Option<Arc<DocumentTree>>,
+    /// XPath-capable tree, built on the first XPath query and rebuilt after
+    /// edits, with the `DocumentTree::edits` count it was built at.
+    xpath_package: RefCell<Option<(usize, sxd_document::Package)>>,
+    /// Compiled selectors keyed by their source string, reused across `select` calls.
+    selector_cache: RefCell<HashMap<String, Selector>>,
+    /// Parse options kept for pickling and copying.
+    base_url_option: Option<Url>,
+    mode: ParseMode,
+}
+
+impl Document {
+    fn parse_with_limit(
+        html: &str,
+        max_size_bytes: Option<usize>,
+        truncate_on_limit: bool,
+        base_url: Option<&str>,
+        mode: ParseMode,
+    ) -> PyResult<Self> {
+        let base_url = base_url.map(parse_base_url).transpose()?;
+        let parsed = ParsedHtml::parse(html, max_size_bytes, truncate_on_limit, base_url, mode)?;
+        Ok(Self::from_parsed(parsed))
+    }
+
+    /// `parse_with_limit`, with the GIL released while html5ever runs.
+    fn parse_detached(
+        py: Python<'_>,
+        html: &str,
+        max_size_bytes: Option<usize>,
+        truncate_on_limit: bool,
+        base_url: Option<&str>,
+        mode: ParseMode,
+    ) -> PyResult<Self> {
+        let base_url = base_url.map(parse_base_url).transpose()?;
+        let parsed = py.detach(|| {
+            ParsedHtml::parse(html, max_size_bytes, truncate_on_limit, base_url, mode)
+        })?;
+        Ok(Self::from_parsed(parsed))
+    }
+
+    fn from_parsed(parsed: ParsedHtml) -> Self {
+        Self {
+            raw_html: parsed.raw_html,
+            tree: Some(DocumentTree::new(parsed.html, parsed.base_url)),
+            xpath_package: RefCell::new(None),
+            selector_cache: RefCell::new(HashMap::new()),
+            base_url_option: parsed.base_url_option,
+            mode: parsed.mode,
+        }
+    }
+
+    /// The parsed tree, or `ValueError` once the document has been closed.
+    fn tree(&self) -> PyResult<&Arc<DocumentTree>> {
+        self.tree
+            .as_ref()
+            .ok_or_else(|| PyValueError::new_err("Document is closed"))
+    }
+
+    /// The base URL for the `base_url` argument of the URL helpers: the
+    /// argument with any `<base href>` applied, else the document base URL.
+    fn resolution_base(&self, html: &Html, base_url: Option<&str>) -> PyResult<Option<Url>> {
+        Ok(match base_url {
+            Some(base_url) => document_base_url(html, Some(parse_base_url(base_url)?)),
+            None => self.tree()?.base_url.clone(),
+        })
+    }
+
+    /// Return the compiled selector for `css`, parsing it only on a cache miss.
+    fn cached_selector(&self, css: &str) -> PyResult<Selector> {
+        if let Some(selector) = self.selector_cache.borrow().get(css) {
+            return Ok(selector.clone());
+        }
+
+        let selector = parse_selector(css)?;
+        self.selector_cache
+            .borrow_mut()
+            .insert(css.to_string(), selector.clone());
+        Ok(selector)
+    }
+
+    /// Return the XPath tree, parsing `current_html()` on first use and again
+    /// after the DOM has been edited.
+    ///
+    /// HTML goes through `sxd_html`. XML documents use the strict
+    /// `sxd_document` parser so names keep their case, falling back to
+    /// `sxd_html` when the XML is not well-formed.
+    fn xpath_package(&self) -> PyResult<Ref<'_, sxd_document::Package>> {
+        let edits = self.tree()?.edits();
+        let stale = self
+            .xpath_package
+            .borrow()
+            .as_ref()
+            .is_none_or(|(built_at, _)| *built_at != edits);
+        if stale {
+            let html = self.current_html()?;
+            let package = match self.mode {
+                ParseMode::Xml => sxd_document::parser::parse(&html)
+                    .unwrap_or_else(|_| sxd_html::parse_html(&html)),
+                _ => sxd_html::parse_html(&html),
+            };
+            *self.xpath_package.borrow_mut() = Some((edits, package));
+        }
+        Ok(Ref::map(self.xpath_package.borrow(), |cached| {
+            &cached.as_ref().expect("xpath tree built above").1
+        }))
+    }
+
+    /// The source HTML, or the serialized tree once it has been edited.
+    fn current_html(&self) -> PyResult<Cow<'_, str>> {
+        Ok(if self.tree()?.edits() == 0 {
+            Cow::Borrowed(&self.raw_html)
+        } else {
+            Cow::Owned(self.to_html()?)
+        })
+    }
+
+    fn select_with(&self, selector: &Selector) -> PyResult<Vec<Element>> {
+        Ok(select_in_tree(self.tree()?, selector))
+    }
+
+    /// Non-blank lines of `to_text()`, or of each element matching `css`
+    /// rendered the same way, as compared by `diff`.
+    fn text_lines(&self, css: Option<&str>) -> PyResult<Vec<String>> {
+        let html = self.tree()?.lock();
+        let text = match css {
+            Some(css) => {
+                let selector = self.cached_selector(css)?;
+                select_elements(&html, &selector)
+                    .map(|el| TextRenderer::render(el, false))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            None => TextRenderer::render(html.root_element(), false),
+        };
+        Ok(text
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// `xpath`, snapshotting at most `limit` matches.
+    fn xpath_limit(&self, expr: &str, limit: usize) -> PyResult<Vec<Element>> {
+        let package = self.xpath_package()?;
+        let document = package.as_document();
+        let elements = evaluate_xpath_elements(document.root().into(), expr, limit)?;
+        Ok(with_base_url(elements, self.tree()?.base_url.as_ref()))
+    }
+
+    /// `select_with` with the GIL released while the tree is searched.
+    fn select_detached(&self, py: Python<'_>, selector: &Selector) -> PyResult<Vec<Element>> {
+        let tree = self.tree()?;
+        Ok(py.detach(|| select_in_tree(tree, selector)))
+    }
+
+    /// Drop all DOM allocations and shrink owned strings.
+    ///
+    /// Elements selected earlier keep their own reference to the old tree.
+    fn release_dom(&mut self) {
+        if self.tree.take().is_none() {
+            return;
+        }
+
+        self.raw_html.clear();
+        self.raw_html.shrink_to_fit();
+        *self.xpath_package.get_mut() = None;
+        self.selector_cache.get_mut().clear();
+    }
+}
+
+#[pymethods]
+impl Document {
+    /// Create a Document from a raw HTML string.
+    ///
+    /// Input larger than `max_size_bytes` (1 GiB by default) raises ValueError,
+    /// or with `truncate_on_limit=True` is cut at the last UTF-8 character
+    /// boundary within the limit before parsing.
+    ///
+    ///     doc = Document("<html>...</html>")
+    ///
+    /// With `strict=True`, any html5ever parse error raises `ParseError`
+    /// naming the first error and its line instead of being recovered from.
+    #[new]
+    #[pyo3(signature = (
+        html,
+        *,
+        max_size_bytes=None,
+        truncate_on_limit=false,
+        base_url=None,
+        strict=false,
+    ))]
+    pub fn new(
+        py: Python<'_>,
+        html: &str,
+        max_size_bytes: Option<usize>,
+        truncate_on_limit: bool,
+        base_url: Option<&str>,
+        strict: bool,
+    ) -> PyResult<Self> {
+        let base_url = base_url.map(parse_base_url).transpose()?;
+        let parsed = py.detach(|| {
+            let parsed = ParsedHtml::parse(
+                html,
+                max_size_bytes,
+                truncate_on_limit,
+                base_url,
+                ParseMode::Document,
+            )?;
+            if strict && let Some(err) = strict_parse_error(&parsed.html, &parsed.raw_html) {
+                return Err(err);
+            }
+            Ok(parsed)
+        })?;
+        Ok(Self::from_parsed(parsed))
+    }
+
+    /// Alternate constructor: Document.from_html(html: str) -> Document
+    #[staticmethod]
+    #[pyo3(signature = (
+        html,
+        *,
+        max_size_bytes=None,
+        truncate_on_limit=false,
+        base_url=None,
+        strict=false,
+    ))]
+    pub fn from_html(
+        py: Python<'_>,
+        html: &str,
+        max_size_bytes: Option<usize>,
+        truncate_on_limit: bool,
+        base_url: Option<&str>,
+        strict: bool,
+    ) -> PyResult<Self> {
+        Self::new(
+            py,
+            html,
+            max_size_bytes,
+            truncate_on_limit,
+            base_url,
+            strict,
+        )
+    }
+
+    /// Alternate constructor: Document.parse_fragment(html: str) -> Document
+    ///
+    /// Parses `html` as a snippet in a `<body>` context, so no `<html>`,
+    /// `<head>` or `<body>` wrappers are added around it:
+    ///
+    ///     items = Document.parse_fragment("<li>a</li><li>b</li>").select("li")
+    #[staticmethod]
+    #[pyo3(signature = (html, *, max_size_bytes=None, truncate_on_limit=false, base_url=None))]
+    pub fn parse_fragment(
+        py: Python<'_>,
+        html: &str,
+        max_size_bytes: Option<usize>,
+        truncate_on_limit: bool,
+        base_url: Option<&str>,
+    ) -> PyResult<Self> {
+        Self::parse_detached(
+            py,
+            html,
+            max_size_bytes,
+            truncate_on_limit,
+            base_url,
+            ParseMode::Fragment,
+        )
+    }
+
+    /// Alternate constructor: Document.parse_xml(xml: str) -> Document
+    ///
+    /// Parses XML or XHTML (RSS, Atom, SVG, sitemaps, ...) with xml5ever
+    /// instead of the HTML parser: tag and attribute names keep their case, no
+    /// `<html>`/`<head>`/`<body>` is added, and CSS type selectors match
+    /// case-sensitively unless the element is in the XHTML namespace.
+    ///
+    ///     feed = Document.parse_xml(rss)
+    ///     titles = [el.text for el in feed.select("item > title")]
+    #[staticmethod]
+    #[pyo3(signature = (xml, *, max_size_bytes=None, truncate_on_limit=false, base_url=None))]
+    pub fn parse_xml(
+        py: Python<'_>,
+        xml: &str,
+        max_size_bytes: Option<usize>,
+        truncate_on_limit: bool,
+        base_url: Option<&str>,
+    ) -> PyResult<Self> {
+        Self::parse_detached(
+            py,
+            xml,
+            max_size_bytes,
+            truncate_on_limit,
+            base_url,
+            ParseMode::Xml,
+        )
+    }
+
+    /// Alternate constructor: Document.from_bytes(data: bytes, encoding=None) -> Document
+    ///
+    /// Without `encoding`, the charset is taken from a byte-order mark or a
+    /// `<meta charset>` declaration. Undeclared input is read as UTF-8, falling
+    /// back to Windows-1252 when it is not valid UTF-8. Unknown encoding labels
+    /// raise ValueError.
+    ///
+    /// `decompress` inflates the data first: `"gzip"`, `"deflate"`, `"br"`, or
+    /// `"auto"` to detect gzip/zlib data and leave anything else untouched.
+    /// Unknown methods and corrupt data raise ValueError.
+    ///
+    ///     doc = Document.from_bytes(response.content)
+    ///     doc = Document.from_bytes(cached_body, decompress="auto")
+    #[staticmethod]
+    #[pyo3(signature = (
+        data,
+        encoding=None,
+        *,
+        max_size_bytes=None,
+        truncate_on_limit=false,
+        base_url=None,
+        decompress=None,
+    ))]
+    pub fn from_bytes(
+        py: Python<'_>,
+        data: &[u8],
+        encoding: Option<&str>,
+        max_size_bytes: Option<usize>,
+        truncate_on_limit: bool,
+        base_url: Option<&str>,
+        decompress: Option<&str>,
+    ) -> PyResult<Self> {
+        let base_url = base_url.map(parse_base_url).transpose()?;
+        let parsed = py.detach(|| {
+            // Decoded text is at least half the size of its bytes (UTF-16 being
+            // the densest case), so inflating past twice the limit only ever
+            // produces input that is over the limit anyway.
+            let limit = effective_max_size(max_size_bytes)
+                .saturating_mul(2)
+                .saturating_add(2);
+            let data = match decompress {
+                Some(method) => decompress_bytes(data, method, limit)?,
+                None => Cow::Borrowed(data),
+            };
+            let html = decode_html_bytes(&data, encoding)?;
+            ParsedHtml::parse(
+                &html,
+                max_size_bytes,
+                truncate_on_limit,
+                base_url,
+                ParseMode::Document,
+            )
+        })?;
+        Ok(Self::from_parsed(parsed))
+    }
+
+    /// Alternate constructor: Document.from_file(path, encoding=None) -> Document
+    ///
+    /// The file is decoded like `from_bytes`, so a UTF-8 BOM or a non-UTF-8
+    /// `<meta charset>` is honoured. Read failures raise OSError naming the path.
+    ///
+    ///     doc = Document.from_file("pages/index.html")
+    #[staticmethod]
+    #[pyo3(signature = (
+        path,
+        encoding=None,
+        *,
+        max_size_bytes=None,
+        truncate_on_limit=false,
+        base_url=None,
+    ))]
+    pub fn from_file(
+        py: Python<'_>,
+        path: PathBuf,
+        encoding: Option<&str>,
+        max_size_bytes: Option<usize>,
+        truncate_on_limit: bool,
+        base_url: Option<&str>,
+    ) -> PyResult<Self> {
+        let data = py
+            .detach(|| std::fs::read(&path))
+            .map_err(|e| PyOSError::new_err(format!("Failed to read {}: {e}", path.display())))?;
+        Self::from_bytes(
+            py,
+            &data,
+            encoding,
+            max_size_bytes,
+            truncate_on_limit,
+            base_url,
+            None,
+        )
+    }
+
+    /// Alternate constructor: Document.from_reader(reader, encoding=None) -> Document
+    ///
+    /// Reads any object with a `read(size)` method (an open file, socket file,
+    /// `gzip.GzipFile`, `io.BytesIO`, ...) in chunks until it is exhausted.
+    /// Bytes are decoded like `from_bytes`; text streams are used as is.
+    /// Reading stops early once the input is certainly over `max_size_bytes`.
+    /// Errors raised by `read()` propagate unchanged.
+    ///
+    ///     with gzip.open("page.html.gz") as f:
+    ///         doc = Document.from_reader(f)
+    #[staticmethod]
+    #[pyo3(signature = (
+        reader,
+        encoding=None,
+        *,
+        max_size_bytes=None,
+        truncate_on_limit=false,
+        base_url=None,
+    ))]
+    pub fn from_reader(
+        py: Python<'_>,
+        reader: &Bound<'_, PyAny>,
+        encoding: Option<&str>,
+        max_size_bytes: Option<usize>,
+        truncate_on_limit: bool,
+        base_url: Option<&str>,
+    ) -> PyResult<Self> {
+        // Same bound as `from_bytes` uses for decompression: anything longer
+        // is over the limit once decoded.
+        let limit = effective_max_size(max_size_bytes)
+            .saturating_mul(2)
+            .saturating_add(2);
+        let mut data = Vec::new();
+        let mut text = String::new();
+        while data.len() + text.len() < limit {
+            let chunk = reader.call_method1("read", (READER_CHUNK_SIZE,))?;
+            if let Ok(bytes) = chunk.cast::<PyBytes>() {
+                if bytes.as_bytes().is_empty() {
+                    break;
+                }
+                data.extend_from_slice(bytes.as_bytes());
+            } else if let Ok(chunk) = chunk.cast::<PyString>() {
+                let chunk = chunk.to_str()?;
+                if chunk.is_empty() {
+                    break;
+                }
+                text.push_str(chunk);
+            } else {
+                return Err(PyTypeError::new_err(format!(
+                    "read() should return bytes or str, not {}",
+                    chunk.get_type().name()?
+                )));
+            }
+        }
+        if !text.is_empty() {
+            if !data.is_empty() {
+                return Err(PyTypeError::new_err(
+                    "read() returned a mix of bytes and str",
+                ));
+            }
+            return Self::parse_detached(
+                py,
+                &text,
+                max_size_bytes,
+                truncate_on_limit,
+                base_url,
+                ParseMode::Document,
+            );
+        }
+        Self::from_bytes(
+            py,
+            &data,
+            encoding,
+            max_size_bytes,
+            truncate_on_limit,
+            base_url,
+            None,
+        )
+    }
+
+    /// Return the original HTML string.
+    ///
+    /// Edits such as `Element.remove` are not reflected here; see `to_html`.
+    #[getter]
+    pub fn html(&self) -> PyResult<&str> {
+        self.tree()?;
+        Ok(&self.raw_html)
+    }
+
+    /// The document serialized from its current tree, including edits made
+    /// with `Element.remove`, `set_attr` and `remove_attr`.
+    ///
+    /// Unlike `html`, this is the normalized DOM: implied `<html>`, `<head>`
+    /// and `<body>` are present and tags are closed. Fragments serialize
+    /// without the implicit wrapper.
+    ///
+    ///     for node in doc.select("script, .ad"):
+    ///         node.remove()
+    ///     cleaned = doc.to_html()
+    pub fn to_html(&self) -> PyResult<String> {
+        let html = self.tree()?.lock();
+        let root = html.root_element();
+        Ok(if is_fragment_wrapper(root) {
+            root.inner_html()
+        } else {
+            html.html()
+        })
+    }
+
+    /// All text content from the document, normalized and joined by spaces.
+    ///
+    /// The contents of `script`, `style`, `template` and `noscript` elements are
+    /// skipped; use `get_text(include_scripts=True)` to keep them.
+    #[getter(text)]
+    pub fn normalized_text(&self) -> PyResult<String> {
+        Ok(element_text(self.tree()?.lock().root_element()))
+    }
+
+    /// Number of whitespace-separated words in `text`, i.e. `len(doc.text.split())`
+    /// without building the string.
+    pub fn word_count(&self) -> PyResult<usize> {
+        let html = self.tree()?.lock();
+        Ok(text_parts(html.root_element(), false)
+            .iter()
+            .map(|part| part.split_whitespace().count())
+            .sum())
+    }
+
+    /// Estimated minutes to read `text` at `wpm` words per minute (200 by
+    /// default). A `wpm` of 0 raises ValueError.
+    ///
+    ///     print(f"{doc.reading_time_minutes():.0f} min read")
+    #[pyo3(signature = (wpm=200))]
+    pub fn reading_time_minutes(&self, wpm: usize) -> PyResult<f64> {
+        if wpm == 0 {
+            return Err(PyValueError::new_err("wpm must be greater than 0"));
+        }
+        Ok(self.word_count()? as f64 / wpm as f64)
+    }
+
+    /// Text of the first `<title>` element with whitespace collapsed, or None.
+    ///
+    ///     print(doc.title)
+    #[getter]
+    pub fn title(&self) -> PyResult<Option<String>> {
+        let selector = self.cached_selector("title")?;
+        let html = self.tree()?.lock();
+        Ok(select_elements(&html, &selector).next().map(element_text))
+    }
+
+    /// Parse errors html5ever recovered from while building the tree.
+    ///
+    /// An empty list means the markup was well-formed as far as the HTML parser
+    /// is concerned; a long list usually explains odd selection results.
+    pub fn errors(&self) -> PyResult<Vec<String>> {
+        let html = self.tree()?.lock();
+        Ok(html.errors.iter().map(|e| e.to_string()).collect())
+    }
+
+    /// Rendering mode the parser picked from the doctype: `"no-quirks"`,
+    /// `"quirks"` or `"limited-quirks"`.
+    #[getter]
+    pub fn quirks_mode(&self) -> PyResult<&'static str> {
+        Ok(match self.tree()?.lock().quirks_mode {
+            QuirksMode::NoQuirks => "no-quirks",
+            QuirksMode::Quirks => "quirks",
+            QuirksMode::LimitedQuirks => "limited-quirks",
+        })
+    }
+
+    /// The `<!DOCTYPE ...>` declaration without its delimiters, or None when
+    /// the document has none.
+    ///
+    /// The name is lowercased and any identifiers follow as
+    /// `PUBLIC "..." "..."` or `SYSTEM "..."`, so the HTML5 doctype reads
+    /// `"html"` and an HTML 4.01 one `html PUBLIC "-//W3C//DTD HTML 4.01//EN" ...`.
+    #[getter]
+    pub fn doctype(&self) -> PyResult<Option<String>> {
+        let html = self.tree()?.lock();
+        Ok(html.tree.root().children().find_map(|node| {
+            let Node::Doctype(doctype) = node.value() else {
+                return None;
+            };
+            let mut declaration = doctype.name.to_ascii_lowercase();
+            match (&*doctype.public_id, &*doctype.system_id) {
+                ("", "") => {}
+                ("", system_id) => declaration.push_str(&format!(" SYSTEM \"{system_id}\"")),
+                (public_id, "") => declaration.push_str(&format!(" PUBLIC \"{public_id}\"")),
+                (public_id, system_id) => {
+                    declaration.push_str(&format!(" PUBLIC \"{public_id}\" \"{system_id}\""))
+                }
+            }
+            Some(declaration)
+        }))
+    }
+
+    /// Readable plain text: block elements and `<br>` start new lines,
+    /// paragraphs and headings are separated by blank lines, list items are
+    /// bulleted (`- `) or numbered (`1. `) and nested lists are indented.
+    ///
+    /// Inline whitespace collapses like in `text`, `<pre>` is kept verbatim, and
+    /// `<head>`, script and style contents are skipped.
+    ///
+    ///     print(doc.to_text())
+    pub fn to_text(&self) -> PyResult<String> {
+        Ok(TextRenderer::render(
+            self.tree()?.lock().root_element(),
+            false,
+        ))
+    }
+
+    /// The document converted to Markdown: headings become `#` lines, `<a>`
+    /// becomes `[text](href)`, `<img>` becomes `![alt](src)`, `<strong>` and
+    /// `<em>` become `**` and `*`, `<code>` and `<pre>` become inline code and
+    /// fenced blocks, and `<blockquote>` lines are prefixed with `> `.
+    ///
+    /// Lists and paragraphs are laid out like `to_text`; other tags degrade to
+    /// their text, with Markdown punctuation in it escaped.
+    ///
+    ///     markdown = doc.to_markdown()
+    pub fn to_markdown(&self) -> PyResult<String> {
+        Ok(TextRenderer::render(
+            self.tree()?.lock().root_element(),
+            true,
+        ))
+    }
+
+    /// The `<body>` contents (or the whole fragment) as HTML that is safe to
+    /// display.
+    ///
+    /// `script`, `style`, `iframe`, `object`, `embed` and similar elements are
+    /// removed with their contents, as are comments, `on*` event-handler
+    /// attributes, `javascript:` URLs and `data:` URLs in `href`, `src` and
+    /// other URL attributes. `allowed_tags` restricts the remaining elements
+    /// (others are unwrapped, keeping their text), and `allowed_attrs` the
+    /// attribute names kept on any element; `style` is only kept when listed
+    /// there. Naming an unsafe element in `allowed_tags` keeps it.
+    ///
+    ///     safe = doc.sanitize(allowed_tags=["p", "a", "b", "i"], allowed_attrs=["href"])
+    #[pyo3(signature = (allowed_tags=None, allowed_attrs=None))]
+    pub fn sanitize(
+        &self,
+        allowed_tags: Option<Vec<String>>,
+        allowed_attrs: Option<Vec<String>>,
+    ) -> PyResult<String> {
+        let html = self.tree()?.lock();
+        let root = html.root_element();
+        let container = root
+            .child_elements()
+            .find(|el| el.value().name() == "body")
+            .unwrap_or(root);
+        Ok(sanitize_children(
+            container,
+            allowed_tags.as_deref(),
+            allowed_attrs.as_deref(),
+        ))
+    }
+
+    /// The parsed tree re-serialized with one tag or text node per line and
+    /// `indent` spaces per nesting level, like BeautifulSoup's `prettify()`.
+    ///
+    /// The output reflects the normalized DOM (implied `<html>`, `<head>` and
+    /// `<body>`, closed tags) and is meant for reading, not round-tripping:
+    /// text nodes are trimmed.
+    ///
+    ///     print(doc.prettify())
+    #[pyo3(signature = (indent=2))]
+    pub fn prettify(&self, indent: usize) -> PyResult<String> {
+        Ok(prettify_tree(self.tree()?.lock().tree.root(), indent))
+    }
+
+    /// The parsed tree re-serialized without insignificant whitespace, the
+    /// compact counterpart of `prettify`.
+    ///
+    /// Whitespace-only text between block-level elements is removed and other
+    /// whitespace runs collapse to a single space, so inline spacing such as
+    /// `<b>a</b> <i>b</i>` survives. `<pre>`, `<textarea>`, `<script>` and
+    /// `<style>` contents are kept unchanged. Like `prettify`, the output
+    /// reflects the normalized DOM.
+    ///
+    ///     cache.set(key, doc.minify())
+    pub fn minify(&self) -> PyResult<String> {
+        Ok(minify_tree(self.tree()?.lock().tree.root()))
+    }
+
+    /// The whole tree under the root element as nested dicts, for JSON export
+    /// or diffing two DOMs. Fragments keep their implicit `<html>` wrapper here,
+    /// so the result always has a single root.
+    ///
+    /// Each element is `{"tag", "attrs", "text", "children"}`, where `text` is
+    /// its direct text (text nodes that are immediate children, concatenated
+    /// as written) and `children` lists element, text and comment nodes in
+    /// order. Text and comment nodes are `{"tag": "#text", "text": ...}` and
+    /// `{"tag": "#comment", "text": ...}`.
+    ///
+    ///     json.dumps(doc.to_tree())
+    pub fn to_tree(&self, py: Python<'_>) -> PyResult<Py<PyDict>> {
+        // Read the tree into plain events first: building Python objects can
+        // run the GC, which must not happen while the tree is locked.
+        let events: Vec<TreeEvent> = {
+            let html = self.tree()?.lock();
+            html.root_element()
+                .traverse()
+                .filter_map(|edge| match edge {
+                    Edge::Open(node) => match node.value() {
+                        Node::Element(element) => Some(TreeEvent::Open(
+                            element.name().to_string(),
+                            element
+                                .attrs()
+                                .map(|(name, value)| (name.to_string(), value.to_string()))
+                                .collect(),
+                        )),
+                        Node::Text(text) => Some(TreeEvent::Text(text.to_string())),
+                        Node::Comment(comment) => Some(TreeEvent::Comment(comment.to_string())),
+                        _ => None,
+                    },
+                    Edge::Close(node) if node.value().is_element() => Some(TreeEvent::Close),
+                    Edge::Close(_) => None,
+                })
+                .collect()
+        };
+
+        let mut stack: Vec<(Bound<'_, PyDict>, Bound<'_, PyList>, String)> = Vec::new();
+        let leaf = |tag: &str, text: &str| -> PyResult<Bound<'_, PyDict>> {
+            let dict = PyDict::new(py);
+            dict.set_item("tag", tag)?;
+            dict.set_item("text", text)?;
+            Ok(dict)
+        };
+        for event in events {
+            match event {
+                TreeEvent::Open(tag, attrs) => {
+                    let dict = PyDict::new(py);
+                    dict.set_item("tag", tag)?;
+                    dict.set_item("attrs", attrs)?;
+                    dict.set_item("text", "")?;
+                    let children = PyList::empty(py);
+                    dict.set_item("children", &children)?;
+                    stack.push((dict, children, String::new()));
+                }
+                TreeEvent::Text(text) => {
+                    if let Some((_, children, direct)) = stack.last_mut() {
+                        direct.push_str(&text);
+                        children.append(leaf("#text", &text)?)?;
+                    }
+                }
+                TreeEvent::Comment(comment) => {
+                    if let Some((_, children, _)) = stack.last() {
+                        children.append(leaf("#comment", &comment)?)?;
+                    }
+                }
+                TreeEvent::Close => {
+                    let (dict, _, direct) = stack.pop().expect("element opened");
+                    dict.set_item("text", direct)?;
+                    match stack.last() {
+                        Some((_, children, _)) => children.append(dict)?,
+                        None => return Ok(dict.unbind()),
+                    }
+                }
+            }
+        }
+        unreachable!("traversal closes the root element")
+    }
+
+    /// Document `text` without the text of elements matching any of `exclude`
+    /// (and everything inside them), for dropping navigation, ads or cookie
+    /// banners.
+    ///
+    ///     body = doc.text_excluding(["nav", ".ad", "#cookie"])
+    pub fn text_excluding(&self, exclude: Vec<String>) -> PyResult<String> {
+        let selectors = exclude
+            .iter()
+            .map(|css| self.cached_selector(css))
+            .collect::<PyResult<Vec<_>>>()?;
+        let html = self.tree()?.lock();
+        Ok(normalize_whitespace(
+            &text_parts_excluding(html.root_element(), false, &selectors).join(" "),
+        ))
+    }
+
+    /// Hex SHA-256 of the normalized document `text`.
+    ///
+    /// Pages that differ only in markup, attributes or whitespace hash the same,
+    /// which makes this a cheap "did the content change" check across crawls.
+    pub fn content_hash(&self) -> PyResult<String> {
+        let mut hasher = Sha256::new();
+        hasher.update(self.normalized_text()?.as_bytes());
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Hex SHA-256 of the element tree's shape: tag names and nesting only,
+    /// ignoring text, attributes and comments.
+    pub fn structure_hash(&self) -> PyResult<String> {
+        let mut hasher = Sha256::new();
+        let html = self.tree()?.lock();
+        for edge in html.root_element().traverse() {
+            match edge {
+                Edge::Open(node) => {
+                    if let Some(element) = node.value().as_element() {
+                        hasher.update(element.name().as_bytes());
+                        hasher.update(b"(");
+                    }
+                }
+                Edge::Close(node) => {
+                    if node.value().is_element() {
+                        hasher.update(b")");
+                    }
+                }
+            }
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Document text with a custom separator between text nodes.
+    ///
+    /// Mirrors BeautifulSoup's `get_text`: whitespace is preserved, and
+    /// `strip=True` trims each text node and drops empty ones. Script and style
+    /// contents are skipped like in `text` unless `include_scripts=True`.
+    /// `normalize` and `form` behave as in `Element.get_text`.
+    ///
+    ///     lines = doc.get_text("\n", strip=True).splitlines()
+    #[pyo3(signature = (
+        separator=" ",
+        strip=false,
+        *,
+        include_scripts=false,
+        normalize=false,
+        form=None,
+    ))]
+    pub fn get_text(
+        &self,
+        separator: &str,
+        strip: bool,
+        include_scripts: bool,
+        normalize: bool,
+        form: Option<&str>,
+    ) -> PyResult<String> {
+        let text = join_text(
+            self.tree()?.lock().root_element(),
+            separator,
+            strip,
+            include_scripts,
+            normalize,
+        );
+        apply_normalization_form(text, form)
+    }
+
+    /// Select all elements matching the given CSS selector.
+    ///
+    /// Returns a list[Element]. Pass `tags` to keep only matches with one of
+    /// those tag names (compared case-insensitively). With
+    /// `include_scripts=True`, the returned elements' `text`, `to_dict` and
+    /// `to_json` keep script and style contents instead of skipping them.
+    ///
+    ///     links = doc.select("a[href]")
+    ///     for el in links:
+    ///         print(el.text, el.attr("href"))
+    ///
+    ///     headlines = doc.select(".headline", tags=["h1", "h2"])
+    #[pyo3(signature = (css, *, tags=None, include_scripts=false))]
+    pub fn select(
+        &self,
+        py: Python<'_>,
+        css: &str,
+        tags: Option<Vec<String>>,
+        include_scripts: bool,
+    ) -> PyResult<Vec<Element>> {
+        let tree = self.tree()?;
+        let selector = self.cached_selector(css)?;
+        let elements = match tags {
+            None => self.select_detached(py, &selector)?,
+            Some(tags) => py.detach(|| {
+                let html = tree.lock();
+                select_elements(&html, &selector)
+                    .filter(|el| {
+                        let name = el.value().name();
+                        tags.iter().any(|tag| name.eq_ignore_ascii_case(tag))
+                    })
+                    .map(|el| Element::from_ref(tree, el))
+                    .collect()
+            }),
+        };
+        Ok(elements
+            .into_iter()
+            .map(|el| el.including_scripts(include_scripts))
+            .collect())
+    }
+
+    /// Run several selectors at once: `{field: css}` in, `{field: [Element]}`
+    /// out, in the same key order.
+    ///
+    /// Every selector is compiled (or taken from the cache) before searching,
+    /// so an invalid one raises before any work is done.
+    ///
+    ///     found = doc.select_map({"title": "h1", "prices": ".price"})
+    pub fn select_map(
+        &self,
+        py: Python<'_>,
+        selectors: IndexMap<String, String>,
+    ) -> PyResult<IndexMap<String, Vec<Element>>> {
+        let tree = self.tree()?;
+        let compiled = selectors
+            .into_iter()
+            .map(|(field, css)| Ok((field, self.cached_selector(&css)?)))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(py.detach(|| {
+            compiled
+                .into_iter()
+                .map(|(field, selector)| (field, select_in_tree(tree, &selector)))
+                .collect()
+        }))
+    }
+
+    /// One value per element matching `container_css`: the text of its first
+    /// descendant matching `field_css`, or that descendant's `attr` attribute.
+    ///
+    /// Containers without a match (or without the attribute) give None, so the
+    /// list lines up with `select(container_css)`.
+    ///
+    ///     prices = doc.pluck(".product", ".price")
+    ///     links = doc.pluck(".product", "a", attr="href")
+    #[pyo3(signature = (container_css, field_css, attr=None))]
+    pub fn pluck(
+        &self,
+        container_css: &str,
+        field_css: &str,
+        attr: Option<&str>,
+    ) -> PyResult<Vec<Option<String>>> {
+        let containers = self.cached_selector(container_css)?;
+        let field = self.cached_selector(field_css)?;
+        let html = self.tree()?.lock();
+        Ok(select_elements(&html, &containers)
+            .map(|container| scoped_value(container, &field, attr))
+            .collect())
+    }
+
+    /// For each element matching `container_css`, every descendant matching
+    /// `field_css`, grouped per container.
+    ///
+    /// Like `Element.select`, each search is scoped to its container's subtree.
+    /// Containers without matches give an empty list, so the outer list lines
+    /// up with `select(container_css)`.
+    ///
+    ///     for tags in doc.select_in(".post", ".tag"):
+    ///         print([tag.text for tag in tags])
+    pub fn select_in(&self, container_css: &str, field_css: &str) -> PyResult<Vec<Vec<Element>>> {
+        let tree = self.tree()?;
+        let containers = self.cached_selector(container_css)?;
+        let field = self.cached_selector(field_css)?;
+        let html = tree.lock();
+        Ok(select_elements(&html, &containers)
+            .map(|container| {
+                container
+                    .select(&field)
+                    .map(|found| Element::from_ref(tree, found))
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Elements matching `css` inside `<template>` contents only, in document
+    /// order.
+    ///
+    /// `select` already reaches template contents, but mixes them with the
+    /// live page. The content fragment is the root of its own tree, so
+    /// selectors are matched against it on its own: `.list > .card` works
+    /// inside a template, `template .card` does not.
+    ///
+    ///     rows = doc.select_templates("tr.row")
+    pub fn select_templates(&self, css: &str) -> PyResult<Vec<Element>> {
+        let tree = self.tree()?;
+        let selector = self.cached_selector(css)?;
+        let html = tree.lock();
+        Ok(select_elements(&html, &selector)
+            .filter(|el| in_template_content(**el))
+            .map(|el| Element::from_ref(tree, el))
+            .collect())
+    }
+
+    /// One dict per element matching `container_css`, built like `pluck` for
+    /// each `{name: (field_css, attr)}` entry in `fields` (`attr` None means
+    /// the text). Keys follow the order of `fields`; missing values are None.
+    ///
+    ///     products = doc.records(".product", {
+    ///         "name": ("h2", None),
+    ///         "url": ("a", "href"),
+    ///     })
+    pub fn records(
+        &self,
+        container_css: &str,
+        fields: IndexMap<String, (String, Option<String>)>,
+    ) -> PyResult<Vec<IndexMap<String, Option<String>>>> {
+        let containers = self.cached_selector(container_css)?;
+        let fields = fields
+            .into_iter()
+            .map(|(name, (css, attr))| Ok((name, self.cached_selector(&css)?, attr)))
+            .collect::<PyResult<Vec<_>>>()?;
+        let html = self.tree()?.lock();
+        Ok(select_elements(&html, &containers)
+            .map(|container| {
+                fields
+                    .iter()
+                    .map(|(name, selector, attr)| {
+                        (
+                            name.clone(),
+                            scoped_value(container, selector, attr.as_deref()),
+                        )
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// The matches of `css` as a CSV string: a header row with `columns`, then
+    /// one row per element.
+    ///
+    /// Each column is `"text"`, `"html"` (inner HTML), `"tag"` or
+    /// `"attr:NAME"`; a missing attribute gives an empty cell. Any other column
+    /// raises ValueError.
+    ///
+    ///     csv_text = doc.select_csv("a", ["text", "attr:href"])
+    pub fn select_csv(&self, css: &str, columns: Vec<String>) -> PyResult<String> {
+        let selector = self.cached_selector(css)?;
+        let specs = columns
+            .iter()
+            .map(|column| ElementField::parse(column, "column"))
+            .collect::<PyResult<Vec<_>>>()?;
+        let csv_error = |e: csv::Error| PyValueError::new_err(format!("Failed to write CSV: {e}"));
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&columns).map_err(csv_error)?;
+        let html = self.tree()?.lock();
+        for el in select_elements(&html, &selector) {
+            writer
+                .write_record(specs.iter().map(|spec| spec.value(el)))
+                .map_err(csv_error)?;
+        }
+        let bytes = writer
+            .into_inner()
+            .map_err(|e| PyValueError::new_err(format!("Failed to write CSV: {e}")))?;
+        Ok(String::from_utf8(bytes).expect("CSV built from UTF-8 strings"))
+    }
+
+    /// The matches of `css`, keeping only the first element for each distinct
+    /// key, in document order.
+    ///
+    /// `by` is `"text"`, `"html"` (inner HTML), `"tag"` or `"attr:NAME"`;
+    /// anything else raises ValueError. With `"attr:NAME"`, elements without
+    /// the attribute are dropped.
+    ///
+    ///     nav = doc.select_unique("a", "attr:href")
+    pub fn select_unique(&self, css: &str, by: &str) -> PyResult<Vec<Element>> {
+        let tree = self.tree()?;
+        let selector = self.cached_selector(css)?;
+        let field = ElementField::parse(by, "key")?;
+        let html = tree.lock();
+        let mut seen = HashSet::new();
+        Ok(select_elements(&html, &selector)
+            .filter(|el| field.lookup(*el).is_some_and(|key| seen.insert(key)))
+            .map(|el| Element::from_ref(tree, el))
+            .collect())
+    }
+
+    /// Like `select`, but returns an iterator that finds matches one at a time.
+    ///
+    /// Nothing is searched until the first `next()`, and stopping early (for
+    /// example with `break`) skips the rest of the document.
+    ///
+    ///     for row in doc.iter_select("tr"):
+    ///         if row.has_class("total"):
+    ///             break
+    pub fn iter_select(&self, css: &str) -> PyResult<SelectIter> {
+        let tree = self.tree()?;
+        let selector = self.cached_selector(css)?;
+        let root = tree.lock().tree.root().id();
+        Ok(SelectIter {
+            tree: Arc::clone(tree),
+            selector,
+            position: Some(root),
+            resume_after_removal: None,
+        })
+    }
+
+    /// Iterate over `(parent_tag, text)` for every text node in document order,
+    /// for custom filtering and joining that `get_text` cannot express.
+    ///
+    /// `parent_tag` is the name of the element holding the text. Like `text`,
+    /// the contents of `script`, `style`, `template` and `noscript` are
+    /// skipped unless `include_scripts=True`. With `strip=True` each text is
+    /// trimmed and whitespace-only nodes are skipped. The walk is lazy, like
+    /// `iter_select`.
+    ///
+    ///     body = " ".join(t for tag, t in doc.iter_text(strip=True) if tag in {"p", "li"})
+    #[pyo3(signature = (*, strip=false, include_scripts=false))]
+    pub fn iter_text(&self, strip: bool, include_scripts: bool) -> PyResult<TextIter> {
+        let tree = self.tree()?;
+        let root = tree.lock().tree.root().id();
+        Ok(TextIter {
+            tree: Arc::clone(tree),
+            position: Some(root),
+            strip,
+            include_scripts,
+        })
+    }
+
+    /// Email addresses from `mailto:` links and the document text, lowercased
+    /// and deduplicated.
+    ///
+    /// Link addresses come first (they are the more reliable source), then
+    /// addresses found in `text`, each group in document order. Script and
+    /// style contents are not scanned.
+    ///
+    ///     doc.emails()  # ["sales@example.com", "info@example.com"]
+    pub fn emails(&self) -> PyResult<Vec<String>> {
+        let email = Regex::new(EMAIL_PATTERN).expect("static regex");
+        let html = self.tree()?.lock();
+        let mut emails = IndexSet::new();
+        for addresses in scheme_hrefs(&html, "mailto:") {
+            emails.extend(
+                addresses
+                    .split(',')
+                    .filter_map(|address| normalize_email(address, &email)),
+            );
+        }
+        let text = element_text(html.root_element());
+        emails.extend(
+            email
+                .find_iter(&text)
+                .map(|found| found.as_str().to_ascii_lowercase()),
+        );
+        Ok(emails.into_iter().collect())
+    }
+
+    /// Phone numbers from `tel:` links and the document text, deduplicated and
+    /// normalized to their digits with any leading `+` kept
+    /// (`"+1 (555) 123-4567"` becomes `"+15551234567"`).
+    ///
+    /// Link numbers come first, then numbers found in `text`. Text matching is
+    /// heuristic: a candidate needs 7 to 15 digits and either a leading `+` or
+    /// separators between digit groups, so bare digit runs (order numbers,
+    /// timestamps) and dates are not reported.
+    ///
+    ///     doc.phones()  # ["+15551234567"]
+    pub fn phones(&self) -> PyResult<Vec<String>> {
+        let phone = Regex::new(PHONE_PATTERN).expect("static regex");
+        let html = self.tree()?.lock();
+        let mut phones: IndexSet<String> = scheme_hrefs(&html, "tel:")
+            .into_iter()
+            .filter_map(normalize_phone)
+            .collect();
+        let text = element_text(html.root_element());
+        for captures in phone.captures_iter(&text) {
+            let found = captures.get(1).expect("phone group");
+            let followed_by_word = text[found.end()..]
+                .chars()
+                .next()
+                .is_some_and(char::is_alphanumeric);
+            let candidate = found.as_str();
+            let grouped =
+                candidate.starts_with('+') || candidate.contains(|c: char| !c.is_ascii_digit());
+            if followed_by_word || !grouped || looks_like_date(candidate) {
+                continue;
+            }
+            phones.extend(normalize_phone(candidate));
+        }
+        Ok(phones.into_iter().collect())
+    }
+
+    /// Skip the first `offset` matches and return up to `limit` of the rest
+    /// (all of them when `limit` is None), for processing results in pages.
+    ///
+    /// Skipped elements are never materialized and the search stops once
+    /// `limit` elements are collected.
+    ///
+    ///     page_two = doc.select_slice(".result", 20, 20)
+    #[pyo3(signature = (css, offset, limit=None))]
+    pub fn select_slice(
+        &self,
+        css: &str,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> PyResult<Vec<Element>> {
+        let tree = self.tree()?;
+        let selector = self.cached_selector(css)?;
+        let html = tree.lock();
+        Ok(select_elements(&html, &selector)
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|el| Element::from_ref(tree, el))
+            .collect())
+    }
+
+    /// The match at `index` in document order, or None when out of range.
+    ///
+    /// Negative indices count from the end like Python lists, so `-1` is the
+    /// last match. Non-negative indices stop the search once reached.
+    ///
+    ///     second = doc.nth("li", 1)
+    pub fn nth(&self, css: &str, index: isize) -> PyResult<Option<Element>> {
+        let tree = self.tree()?;
+        let selector = self.cached_selector(css)?;
+        let html = tree.lock();
+        let mut matches = select_elements(&html, &selector);
+        let found = match usize::try_from(index) {
+            Ok(index) => matches.nth(index),
+            Err(_) => {
+                let matches: Vec<_> = matches.collect();
+                matches
+                    .len()
+                    .checked_sub(index.unsigned_abs())
+                    .map(|index| matches[index])
+            }
+        };
+        Ok(found.map(|el| Element::from_ref(tree, el)))
+    }
+
+    /// The last matching element, or None if nothing matches. Same as
+    /// `nth(css, -1)`.
+    pub fn last(&self, css: &str) -> PyResult<Option<Element>> {
+        let tree = self.tree()?;
+        let selector = self.cached_selector(css)?;
+        let html = tree.lock();
+        Ok(select_elements(&html, &selector)
+            .last()
+            .map(|el| Element::from_ref(tree, el)))
+    }
+
+    /// Return the first matching element, or None if nothing matches.
+    ///
+    ///     first_link = doc.select_first("a[href]")
+    ///
+    /// `include_scripts` behaves as in `select`.
+    #[pyo3(signature = (css, *, include_scripts=false))]
+    pub fn select_first(&self, css: &str, include_scripts: bool) -> PyResult<Option<Element>> {
+        let tree = self.tree()?;
+        let selector = self.cached_selector(css)?;
+        let html = tree.lock();
+        Ok(select_elements(&html, &selector)
+            .next()
+            .map(|el| Element::from_ref(tree, el).including_scripts(include_scripts)))
+    }
+
+    /// Elements matching `css` whose normalized `text` contains `substring`.
+    ///
+    /// With `case_insensitive=True` both sides are lowercased before comparing.
+    ///
+    ///     submit = doc.select_containing("button", "submit", case_insensitive=True)
+    #[pyo3(signature = (css, substring, case_insensitive=false))]
+    pub fn select_containing(
+        &self,
+        css: &str,
+        substring: &str,
+        case_insensitive: bool,
+    ) -> PyResult<Vec<Element>> {
+        let tree = self.tree()?;
+        let selector = self.cached_selector(css)?;
+        let needle = if case_insensitive {
+            substring.to_lowercase()
+        } else {
+            substring.to_string()
+        };
+        let html = tree.lock();
+        Ok(select_elements(&html, &selector)
+            .filter(|el| {
+                let text = element_text(*el);
+                if case_insensitive {
+                    text.to_lowercase().contains(&needle)
+                } else {
+                    text.contains(&needle)
+                }
+            })
+            .map(|el| Element::from_ref(tree, el))
+            .collect())
+    }
+
+    /// Elements matching `css` whose normalized `text` matches the regular
+    /// expression `pattern`.
+    ///
+    /// Like `select_attr_regex`, the pattern is searched anywhere in the text and
+    /// invalid patterns raise ValueError.
+    ///
+    ///     prices = doc.select_text_regex("li", r"^\$\d+\.\d{2}$")
+    pub fn select_text_regex(&self, css: &str, pattern: &str) -> PyResult<Vec<Element>> {
+        let tree = self.tree()?;
+        let selector = self.cached_selector(css)?;
+        let regex = Regex::new(pattern)
+            .map_err(|e| PyValueError::new_err(format!("Invalid regex {pattern:?}: {e}")))?;
+        let html = tree.lock();
+        Ok(select_elements(&html, &selector)
+            .filter(|el| regex.is_match(&element_text(*el)))
+            .map(|el| Element::from_ref(tree, el))
+            .collect())
+    }
+
+    /// JSON array of `Element.to_json` objects for every match of `css`.
+    ///
+    ///     print(doc.select_json("a[href]"))
+    pub fn select_json(&self, css: &str) -> PyResult<String> {
+        let selector = self.cached_selector(css)?;
+        let elements = self
+            .select_with(&selector)?
+            .iter()
+            .map(Element::json_value)
+            .collect();
+        Ok(serde_json::Value::Array(elements).to_string())
+    }
+
+    /// Number of elements matching `css`, without building any Element objects.
+    ///
+    ///     if doc.count("tr") > 100: ...
+    pub fn count(&self, css: &str) -> PyResult<usize> {
+        let selector = self.cached_selector(css)?;
+        let html = self.tree()?.lock();
+        Ok(select_elements(&html, &selector).count())
+    }
+
+    /// Whether any element matches `css`; stops at the first match.
+    ///
+    ///     if doc.exists(".captcha"): ...
+    pub fn exists(&self, css: &str) -> PyResult<bool> {
+        let selector = self.cached_selector(css)?;
+        let html = self.tree()?.lock();
+        Ok(select_elements(&html, &selector).next().is_some())
+    }
+
+    /// Return at most `limit` matching elements, stopping the search early.
+    ///
+    ///     top_ten = doc.select_limit(".result", 10)
+    pub fn select_limit(&self, css: &str, limit: usize) -> PyResult<Vec<Element>> {
+        let tree = self.tree()?;
+        let selector = self.cached_selector(css)?;
+        let html = tree.lock();
+        Ok(select_elements(&html, &selector)
+            .take(limit)
+            .map(|el| Element::from_ref(tree, el))
+            .collect())
+    }
+
+    /// Return the first matching element, or None if nothing matches.
+    ///
+    ///     first_link = doc.find("a[href]")
+    ///     if first_link:
+    ///         print(first_link.text)
+    ///
+    /// `include_scripts` behaves as in `select`.
+    #[pyo3(signature = (css, *, include_scripts=false))]
+    pub fn find(&self, css: &str, include_scripts: bool) -> PyResult<Option<Element>> {
+        self.select_first(css, include_scripts)
+    }
+
+    /// Alias for `find(css)`, for code ported from BeautifulSoup.
+    ///
+    ///     title = doc.select_one("h1.title")
+    #[pyo3(signature = (css, *, include_scripts=false))]
+    pub fn select_one(&self, css: &str, include_scripts: bool) -> PyResult<Option<Element>> {
+        self.select_first(css, include_scripts)
+    }
+
+    /// Alias for `select(css)`, for code ported from BeautifulSoup.
+    ///
+    /// Only CSS selectors are accepted; for BeautifulSoup-style tag and
+    /// attribute filters use `find_by_attrs`.
+    ///
+    ///     for row in doc.find_all("tr"):
+    ///         ...
+    #[pyo3(signature = (css, *, include_scripts=false))]
+    pub fn find_all(
+        &self,
+        py: Python<'_>,
+        css: &str,
+        include_scripts: bool,
+    ) -> PyResult<Vec<Element>> {
+        self.select(py, css, None, include_scripts)
+    }
+
+    /// The root element: `<html>` for HTML documents, the top-level element
+    /// for XML.
+    ///
+    /// Fragments skip their implicit `<html>` wrapper and return their single
+    /// top-level element; a fragment with several (or none) raises ValueError,
+    /// since it has no one root. Use `walk()` or `select()` there instead.
+    ///
+    /// A starting point for manual traversal or scoped selection:
+    ///
+    ///     for child in doc.root().children():
+    ///         print(child.tag)  # "head", "body"
+    pub fn root(&self) -> PyResult<Element> {
+        let tree = self.tree()?;
+        let html = tree.lock();
+        let root = html.root_element();
+        if self.mode != ParseMode::Fragment {
+            return Ok(Element::from_ref(tree, root));
+        }
+        let mut top = root.child_elements();
+        match (top.next(), top.next()) {
+            (Some(el), None) => Ok(Element::from_ref(tree, el)),
+            _ => Err(PyValueError::new_err(format!(
+                "root() needs a fragment with one top-level element, found {}",
+                root.child_elements().count()
+            ))),
+        }
+    }
+
+    /// Every element in the document in pre-order (document order), starting
+    /// with `<html>`. `Element.depth()` gives each element's nesting level.
+    ///
+    /// Use it for extraction logic selectors cannot express:
+    ///
+    ///     for el in doc.walk():
+    ///         if el.tag == "h2":
+    ///             ...
+    pub fn walk(&self) -> PyResult<Vec<Element>> {
+        let tree = self.tree()?;
+        let html = tree.lock();
+        Ok(html
+            .tree
+            .root()
+            .descendants()
+            .filter_map(ElementRef::wrap)
+            .filter(|el| !is_fragment_wrapper(*el))
+            .map(|el| Element::from_ref(tree, el))
+            .collect())
+    }
+
+    /// Text of every comment in the document, in document order, as written
+    /// between `<!--` and `-->` (surrounding whitespace included).
+    ///
+    ///     doc.comments()  # [" build 1234 "]
+    pub fn comments(&self) -> PyResult<Vec<String>> {
+        let html = self.tree()?.lock();
+        Ok(html
+            .tree
+            .root()
+            .descendants()
+            .filter_map(|node| match node.value() {
+                Node::Comment(comment) => Some(comment.comment.to_string()),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Return the element whose `id` attribute equals `id`, or None.
+    ///
+    /// The id is escaped before building the selector, so values such as
+    /// "123" or "a.b" work as-is.
+    ///
+    ///     header = doc.by_id("main-header")
+    pub fn by_id(&self, id: &str) -> PyResult<Option<Element>> {
+        if id.is_empty() {
+            return Ok(None);
+        }
+        self.select_first(&format!("#{}", escape_css_identifier(id)), false)
     }
 
-    for child in node.children() {
-        if let Some(found) = find_first_element_by_name(child, name) {
-            return Some(found);
+    /// Elements whose `class` attribute contains `name`.
+    ///
+    /// Like `by_id`, the name is escaped, so classes such as "md:flex" or
+    /// "w-1/2" need no CSS knowledge.
+    ///
+    ///     cards = doc.by_class("card")
+    pub fn by_class(&self, py: Python<'_>, name: &str) -> PyResult<Vec<Element>> {
+        if name.is_empty() {
+            return Ok(Vec::new());
         }
+        self.select(
+            py,
+            &format!(".{}", escape_css_identifier(name)),
+            None,
+            false,
+        )
     }
 
-    None
-}
+    /// Elements with the given tag name, in document order.
+    ///
+    ///     paragraphs = doc.by_tag("p")
+    pub fn by_tag(&self, py: Python<'_>, name: &str) -> PyResult<Vec<Element>> {
+        if name.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.select(py, &escape_css_identifier(name), None, false)
+    }
 
-fn evaluate_fragment_xpath(html: &str, expr: &str) -> PyResult<Vec<Element>> {
-    let wrapped = format!("<xpath-fragment>{}</xpath-fragment>", html);
-    let package = sxd_html::parse_html(&wrapped);
-    let document = package.as_document();
+    /// Elements matching constraints given as data, like BeautifulSoup's
+    /// `find_all("a", {"class": "x"})`.
+    ///
+    /// `tag` (any tag when None) is compared case-insensitively. Every entry in
+    /// `attrs` must equal the element's attribute value exactly, except
+    /// `class`, which matches when it is one of the element's classes.
+    ///
+    ///     buttons = doc.find_by_attrs("button", {"type": "submit"})
+    #[pyo3(signature = (tag=None, attrs=None))]
+    pub fn find_by_attrs(
+        &self,
+        tag: Option<&str>,
+        attrs: Option<IndexMap<String, String>>,
+    ) -> PyResult<Vec<Element>> {
+        let tree = self.tree()?;
+        let attrs = attrs.unwrap_or_default();
+        let html = tree.lock();
+        Ok(html
+            .tree
+            .root()
+            .descendants()
+            .filter_map(ElementRef::wrap)
+            .filter(|el| !is_fragment_wrapper(*el))
+            .filter(|el| {
+                let element = el.value();
+                tag.is_none_or(|tag| element.name().eq_ignore_ascii_case(tag))
+                    && attrs
+                        .iter()
+                        .all(|(name, expected)| match element.attr(name) {
+                            Some(value) if name == "class" => value
+                                .split_ascii_whitespace()
+                                .any(|class| class == expected),
+                            Some(value) => value == expected,
+                            None => false,
+                        })
+            })
+            .map(|el| Element::from_ref(tree, el))
+            .collect())
+    }
 
-    let Some(wrapper) = find_first_element_by_name(document.root().into(), "xpath-fragment") else {
-        return Err(PyValueError::new_err(
-            "Failed to parse HTML fragment for XPath evaluation",
-        ));
-    };
+    /// Elements (of `tag`, or any tag when None) whose `name` attribute matches
+    /// the regular expression `pattern`.
+    ///
+    /// The pattern uses Rust `regex` syntax and is searched anywhere in the
+    /// value, so anchor it with `^`/`$` for a full match. Invalid patterns raise
+    /// ValueError.
+    ///
+    ///     products = doc.select_attr_regex("a", "href", r"^/product/\d+")
+    pub fn select_attr_regex(
+        &self,
+        tag: Option<&str>,
+        name: &str,
+        pattern: &str,
+    ) -> PyResult<Vec<Element>> {
+        let tree = self.tree()?;
+        let regex = Regex::new(pattern)
+            .map_err(|e| PyValueError::new_err(format!("Invalid regex {pattern:?}: {e}")))?;
+        let html = tree.lock();
+        Ok(html
+            .tree
+            .root()
+            .descendants()
+            .filter_map(ElementRef::wrap)
+            .filter(|el| !is_fragment_wrapper(*el))
+            .filter(|el| {
+                let element = el.value();
+                tag.is_none_or(|tag| element.name().eq_ignore_ascii_case(tag))
+                    && element
+                        .attr(name)
+                        .is_some_and(|value| regex.is_match(value))
+            })
+            .map(|el| Element::from_ref(tree, el))
+            .collect())
+    }
 
-    evaluate_xpath_elements(wrapper, expr)
-}
+    /// Elements of any tag that have the attribute `name`, in document order.
+    ///
+    /// With `non_empty=True` only elements whose value is not empty or
+    /// whitespace are kept, which a CSS `[name]` selector cannot express.
+    ///
+    ///     described = doc.having_attr("aria-label", non_empty=True)
+    #[pyo3(signature = (name, non_empty=false))]
+    pub fn having_attr(&self, name: &str, non_empty: bool) -> PyResult<Vec<Element>> {
+        let tree = self.tree()?;
+        let html = tree.lock();
+        Ok(html
+            .tree
+            .root()
+            .descendants()
+            .filter_map(ElementRef::wrap)
+            .filter(|el| !is_fragment_wrapper(*el))
+            .filter(|el| {
+                el.value()
+                    .attr(name)
+                    .is_some_and(|value| !non_empty || !value.trim().is_empty())
+            })
+            .map(|el| Element::from_ref(tree, el))
+            .collect())
+    }
 
-/// A parsed HTML document with convenient, Pythonic selectors.
-///
-/// Example:
-///
-///     from scraper_rs import Document
-///
-///     doc = Document("<html><body><a href='/x'>link</a></body></html>")
-///     a = doc.find("a")
-///     print(a.text, a.attr("href"))
-#[pyclass(module = "scraper_rs", unsendable)]
-pub struct Document {
-    raw_html: String,
-    html: Html,
-    xpath_package: sxd_document::Package,
-    closed: bool,
-}
+    /// `(text, href)` for every `<a href>` in the document, in document order.
+    ///
+    /// Anchors without an `href` are skipped; the text is normalized like `text`.
+    /// With `absolute=True` each href is resolved like `Element.abs_attr`, and
+    /// links that cannot be resolved are skipped.
+    ///
+    ///     for text, href in doc.links(absolute=True):
+    ///         print(text, href)
+    #[pyo3(signature = (*, absolute=false))]
+    pub fn links(&self, absolute: bool) -> PyResult<Vec<(String, String)>> {
+        let tree = self.tree()?;
+        let selector = self.cached_selector("a[href]")?;
+        let base_url = tree.base_url.as_ref();
+        let html = tree.lock();
+        Ok(select_elements(&html, &selector)
+            .filter_map(|el| {
+                let href = el.value().attr("href")?;
+                let href = if absolute {
+                    resolve_url(base_url, href)?
+                } else {
+                    href.to_string()
+                };
+                Some((element_text(el), href))
+            })
+            .collect())
+    }
 
-impl Document {
-    fn parse_with_limit(
-        html: &str,
-        max_size_bytes: Option<usize>,
-        truncate_on_limit: bool,
-    ) -> PyResult<Self> {
-        let max_size_bytes = effective_max_size(max_size_bytes);
-        let html_to_parse = ensure_within_size_limit(html, max_size_bytes, truncate_on_limit)?;
+    /// Every URL referenced from a URL-bearing attribute, deduplicated in
+    /// document order.
+    ///
+    /// Reads `href`, `src`, `data-src`, `poster`, `action`, `formaction` and
+    /// `cite`, plus each candidate of `srcset`/`data-srcset`, on any element.
+    /// URLs are resolved against `base_url` (with any `<base href>` applied on
+    /// top), or against the document's own `base_url` when not given; values
+    /// that cannot be resolved are skipped. Without any base the raw values are
+    /// returned. Empty values are ignored.
+    ///
+    ///     doc.extract_urls("https://example.com/")
+    #[pyo3(signature = (base_url=None))]
+    pub fn extract_urls(&self, base_url: Option<&str>) -> PyResult<Vec<String>> {
+        let html = self.tree()?.lock();
+        let base_url = self.resolution_base(&html, base_url)?;
+        let mut urls = IndexSet::new();
+        for el in html.tree.root().descendants().filter_map(ElementRef::wrap) {
+            for (name, value) in el.value().attrs() {
+                let values = if URL_ATTRIBUTES.contains(&name) {
+                    vec![value.trim()]
+                } else if SRCSET_ATTRIBUTES.contains(&name) {
+                    srcset_urls(value)
+                } else {
+                    continue;
+                };
+                for value in values.into_iter().filter(|value| !value.is_empty()) {
+                    let url = match &base_url {
+                        Some(base) => resolve_url(Some(base), value),
+                        None => Some(value.to_string()),
+                    };
+                    urls.extend(url);
+                }
+            }
+        }
+        Ok(urls.into_iter().collect())
+    }
 
-        // Parse using the Cow reference, then convert to owned String
-        let xpath_package = sxd_html::parse_html(html_to_parse.as_ref());
-        let html_parsed = Html::parse_document(html_to_parse.as_ref());
+    /// One dict per `<img>` in document order with its `src`, `alt`, `width`
+    /// and `height`; keys are only present when the attribute is.
+    ///
+    /// Without a `src`, the first `srcset` candidate is used instead. `src` is
+    /// resolved like `extract_urls`: against `base_url` when given, otherwise
+    /// the document's own base URL, and left as written when there is no base.
+    ///
+    ///     for image in doc.images("https://example.com/"):
+    ///         print(image["src"], image.get("alt"))
+    #[pyo3(signature = (base_url=None))]
+    pub fn images(&self, base_url: Option<&str>) -> PyResult<Vec<IndexMap<String, String>>> {
+        let selector = self.cached_selector("img")?;
+        let html = self.tree()?.lock();
+        let base_url = self.resolution_base(&html, base_url)?;
+        Ok(select_elements(&html, &selector)
+            .map(|el| {
+                let element = el.value();
+                let src = element
+                    .attr("src")
+                    .map(str::trim)
+                    .filter(|src| !src.is_empty())
+                    .or_else(|| {
+                        element
+                            .attr("srcset")
+                            .and_then(|v| srcset_urls(v).first().copied())
+                    })
+                    .and_then(|src| match &base_url {
+                        Some(base) => resolve_url(Some(base), src),
+                        None => Some(src.to_string()),
+                    });
+                let mut image = IndexMap::new();
+                image.extend(src.map(|src| ("src".to_string(), src)));
+                for name in ["alt", "width", "height"] {
+                    if let Some(value) = element.attr(name) {
+                        image.insert(name.to_string(), value.to_string());
+                    }
+                }
+                image
+            })
+            .collect())
+    }
 
-        Ok(Self {
-            raw_html: html_to_parse.into_owned(),
-            html: html_parsed,
-            xpath_package,
-            closed: false,
-        })
+    /// The base URL used to resolve relative links, or None.
+    ///
+    /// This is the `base_url` passed to the constructor, overridden by a
+    /// `<base href>` element when the document has one.
+    #[getter]
+    pub fn base_url(&self) -> PyResult<Option<String>> {
+        Ok(self.tree()?.base_url.as_ref().map(Url::to_string))
     }
 
-    /// Drop all DOM allocations and shrink owned strings.
-    fn release_dom(&mut self) {
-        if self.closed {
-            return;
+    /// Parsed contents of every `<script type="application/ld+json">` block.
+    ///
+    /// Each block becomes the matching Python object (usually a dict or list).
+    /// Blocks that are not valid JSON are skipped; pass a list as `errors` to
+    /// collect a message for each one.
+    ///
+    ///     problems = []
+    ///     for data in doc.jsonld(errors=problems):
+    ///         print(data.get("@type"))
+    #[pyo3(signature = (*, errors=None))]
+    pub fn jsonld(
+        &self,
+        py: Python<'_>,
+        errors: Option<Bound<'_, PyList>>,
+    ) -> PyResult<Vec<Py<PyAny>>> {
+        let selector = self.cached_selector(r#"script[type="application/ld+json" i]"#)?;
+        let blocks: Vec<String> = {
+            let html = self.tree()?.lock();
+            select_elements(&html, &selector)
+                .map(|el| el.text().collect())
+                .collect()
+        };
+
+        let mut results = Vec::new();
+        for (index, block) in blocks.iter().enumerate() {
+            match serde_json::from_str::<serde_json::Value>(block) {
+                Ok(value) => results.push(json_to_py(py, &value)?.unbind()),
+                Err(e) => {
+                    if let Some(errors) = &errors {
+                        errors.append(format!("JSON-LD block {index}: {e}"))?;
+                    }
+                }
+            }
         }
+        Ok(results)
+    }
 
-        self.raw_html.clear();
-        self.raw_html.shrink_to_fit();
-        self.html = Html::parse_document("");
-        self.xpath_package = sxd_html::parse_html("");
-        self.closed = true;
+    /// `<meta>` tags as a dict of `name` (or `property`) to `content`.
+    ///
+    /// Covers both `<meta name="description">` and Open Graph style
+    /// `<meta property="og:title">` tags. When a key appears more than once the
+    /// last tag in the document wins.
+    ///
+    ///     doc.meta().get("og:title")
+    pub fn meta(&self) -> PyResult<HashMap<String, String>> {
+        let selector = self.cached_selector("meta[content]")?;
+        let html = self.tree()?.lock();
+        Ok(select_elements(&html, &selector)
+            .filter_map(|el| {
+                let key = el.attr("name").or_else(|| el.attr("property"))?;
+                let content = el.attr("content")?;
+                Some((key.to_string(), content.to_string()))
+            })
+            .collect())
     }
-}
 
-#[pymethods]
-impl Document {
-    /// Create a Document from a raw HTML string.
+    /// The canonical URL: the first `<link rel="canonical" href>`, falling back
+    /// to the `og:url` meta tag; None when the page declares neither.
     ///
-    ///     doc = Document("<html>...</html>")
-    #[new]
-    #[pyo3(signature = (html, *, max_size_bytes=None, truncate_on_limit=false))]
-    pub fn new(
-        html: &str,
-        max_size_bytes: Option<usize>,
-        truncate_on_limit: bool,
-    ) -> PyResult<Self> {
-        Self::parse_with_limit(html, max_size_bytes, truncate_on_limit)
+    /// The URL is resolved like `extract_urls`: against `base_url` when given,
+    /// otherwise the document's own base URL, and returned as written when
+    /// there is no base at all. An unresolvable value gives None.
+    ///
+    ///     doc.canonical_url("https://example.com/page?utm_source=x")
+    #[pyo3(signature = (base_url=None))]
+    pub fn canonical_url(&self, base_url: Option<&str>) -> PyResult<Option<String>> {
+        let links = self.cached_selector("link[rel][href]")?;
+        let metas = self.cached_selector("meta[property][content]")?;
+        let html = self.tree()?.lock();
+        let base_url = self.resolution_base(&html, base_url)?;
+        let canonical = select_elements(&html, &links)
+            .find(|el| {
+                el.attr("rel").is_some_and(|rel| {
+                    rel.split_ascii_whitespace()
+                        .any(|token| token.eq_ignore_ascii_case("canonical"))
+                })
+            })
+            .and_then(|el| el.attr("href"))
+            .or_else(|| {
+                select_elements(&html, &metas)
+                    .find(|el| el.attr("property") == Some("og:url"))
+                    .and_then(|el| el.attr("content"))
+            })
+            .map(str::trim)
+            .filter(|url| !url.is_empty());
+        Ok(canonical.and_then(|url| match &base_url {
+            Some(base) => resolve_url(Some(base), url),
+            None => Some(url.to_string()),
+        }))
     }
 
-    /// Alternate constructor: Document.from_html(html: str) -> Document
-    #[staticmethod]
-    #[pyo3(signature = (html, *, max_size_bytes=None, truncate_on_limit=false))]
-    pub fn from_html(
-        html: &str,
-        max_size_bytes: Option<usize>,
-        truncate_on_limit: bool,
-    ) -> PyResult<Self> {
-        Self::parse_with_limit(html, max_size_bytes, truncate_on_limit)
+    /// The document language: the root `<html lang>`, falling back to a
+    /// `<meta http-equiv="Content-Language">` pragma; None if neither is set.
+    ///
+    /// Same as `doc.root().lang()`; use `Element.lang()` for the language a
+    /// particular element inherits.
+    ///
+    ///     doc.lang()  # "en-US"
+    pub fn lang(&self) -> PyResult<Option<String>> {
+        Ok(element_lang(self.tree()?.lock().root_element()))
     }
 
-    /// Return the original HTML string.
-    #[getter]
-    pub fn html(&self) -> &str {
-        &self.raw_html
+    /// Open Graph and Twitter card metadata: every `<meta>` whose `property`
+    /// or `name` starts with `og:` or `twitter:`, keyed by that full name
+    /// (prefix kept) with its `content`.
+    ///
+    /// Unlike `meta()`, the first tag wins when a key repeats, matching how
+    /// Open Graph treats the first `og:image` of several as the primary one.
+    ///
+    ///     card = doc.opengraph()
+    ///     card.get("og:title"), card.get("twitter:card")
+    pub fn opengraph(&self) -> PyResult<IndexMap<String, String>> {
+        let selector = self.cached_selector("meta[content]")?;
+        let html = self.tree()?.lock();
+        let mut card = IndexMap::new();
+        for el in select_elements(&html, &selector) {
+            let key = [el.attr("property"), el.attr("name")]
+                .into_iter()
+                .flatten()
+                .find(|key| key.starts_with("og:") || key.starts_with("twitter:"));
+            if let (Some(key), Some(content)) = (key, el.attr("content")) {
+                card.entry(key.to_string())
+                    .or_insert_with(|| content.to_string());
+            }
+        }
+        Ok(card)
     }
 
-    /// All text content from the document, normalized and joined by spaces.
-    #[getter]
-    pub fn text(&self) -> String {
-        self.html
-            .root_element()
-            .text()
-            .collect::<Vec<_>>()
-            .join(" ")
-            .split_whitespace()
-            .collect::<Vec<_>>()
-            .join(" ")
+    /// The charset declared by the first `<meta charset>` or
+    /// `<meta http-equiv="Content-Type">` tag, lowercased; None if undeclared.
+    ///
+    ///     doc.charset()  # "utf-8"
+    pub fn charset(&self) -> PyResult<Option<String>> {
+        let selector = self.cached_selector("meta")?;
+        let html = self.tree()?.lock();
+        Ok(select_elements(&html, &selector).find_map(|el| {
+            if let Some(charset) = el.attr("charset") {
+                let charset = charset.trim();
+                return (!charset.is_empty()).then(|| charset.to_ascii_lowercase());
+            }
+            let is_content_type = el
+                .attr("http-equiv")
+                .is_some_and(|value| value.trim().eq_ignore_ascii_case("content-type"));
+            if !is_content_type {
+                return None;
+            }
+            let content = el.attr("content")?.to_ascii_lowercase();
+            charset_label(content.as_bytes())
+                .map(|label| String::from_utf8_lossy(label).into_owned())
+        }))
     }
 
-    /// Select all elements matching the given CSS selector.
+    /// Every `<form>` in the document with its fields and their default values.
     ///
-    /// Returns a list[Element].
+    /// Each form is a dict with `action` (None when missing), `method`
+    /// (lowercased, "get" by default) and `fields`. Each field has `name`,
+    /// `type`, `value`, `checked` and `options` (the option values of a
+    /// `<select>`, empty for other controls).
     ///
-    ///     links = doc.select("a[href]")
-    ///     for el in links:
-    ///         print(el.text, el.attr("href"))
-    pub fn select(&self, css: &str) -> PyResult<Vec<Element>> {
-        let selector = parse_selector(css)?;
-        Ok(self
-            .html
-            .select(&selector)
-            .map(snapshot_element)
-            .collect::<Vec<_>>())
+    ///     form = doc.forms()[0]
+    ///     data = {f["name"]: f["value"] for f in form["fields"] if f["name"]}
+    pub fn forms(&self, py: Python<'_>) -> PyResult<Vec<Py<PyDict>>> {
+        let selector = self.cached_selector("form")?;
+        let forms: Vec<_> = {
+            let html = self.tree()?.lock();
+            select_elements(&html, &selector)
+                .map(FormData::read)
+                .collect()
+        };
+        forms.into_iter().map(|form| form.into_py(py)).collect()
     }
 
-    /// Return the first matching element, or None if nothing matches.
+    /// Every `<table>` in the document as a list of row dicts, in document order.
     ///
-    ///     first_link = doc.select_first("a[href]")
-    pub fn select_first(&self, css: &str) -> PyResult<Option<Element>> {
-        Ok(self.select(css)?.into_iter().next())
+    /// The first row (`th` or `td` cells, with or without `<thead>`) supplies the
+    /// keys and each later row maps its cells to them. Short rows are padded with
+    /// empty strings, a `colspan` repeats the cell's text, blank headers become
+    /// the column index and duplicates get `.1`, `.2` suffixes.
+    ///
+    ///     for table in doc.tables():
+    ///         for row in table:
+    ///             print(row["Name"])
+    pub fn tables(&self, py: Python<'_>) -> PyResult<Vec<Vec<Py<PyDict>>>> {
+        let selector = self.cached_selector("table")?;
+        let records: Vec<_> = {
+            let html = self.tree()?.lock();
+            select_elements(&html, &selector)
+                .map(table_records)
+                .collect()
+        };
+        records
+            .into_iter()
+            .map(|table| records_to_py(py, table))
+            .collect()
     }
 
-    /// Return the first matching element, or None if nothing matches.
+    /// Value of attribute `name` for every element matching `css`, in document order.
     ///
-    ///     first_link = doc.find("a[href]")
-    ///     if first_link:
-    ///         print(first_link.text)
-    pub fn find(&self, css: &str) -> PyResult<Option<Element>> {
-        self.select_first(css)
+    /// Matched elements without the attribute produce None, so the result lines
+    /// up with `select(css)`:
+    ///
+    ///     hrefs = doc.select_attr("a", "href")
+    pub fn select_attr(&self, css: &str, name: &str) -> PyResult<Vec<Option<String>>> {
+        let selector = self.cached_selector(css)?;
+        let html = self.tree()?.lock();
+        Ok(select_elements(&html, &selector)
+            .map(|el| el.value().attr(name).map(str::to_string))
+            .collect())
+    }
+
+    /// For every element matching `css`, a dict of just the attributes in
+    /// `names` (None when missing), in the order given.
+    ///
+    /// Only the requested values are copied, unlike reading `attrs` of each
+    /// match.
+    ///
+    ///     for link in doc.select_attrs("a", ["href", "title", "data-id"]):
+    ///         print(link["href"], link["data-id"])
+    pub fn select_attrs(
+        &self,
+        css: &str,
+        names: Vec<String>,
+    ) -> PyResult<Vec<IndexMap<String, Option<String>>>> {
+        let selector = self.cached_selector(css)?;
+        let html = self.tree()?.lock();
+        Ok(select_elements(&html, &selector)
+            .map(|el| {
+                names
+                    .iter()
+                    .map(|name| (name.clone(), el.value().attr(name).map(str::to_string)))
+                    .collect()
+            })
+            .collect())
     }
 
     /// Shorthand for `select(css)`; more “requests-html” style.
     ///
     ///     doc.css("div.item")
-    pub fn css(&self, css: &str) -> PyResult<Vec<Element>> {
-        self.select(css)
+    #[pyo3(signature = (css, *, include_scripts=false))]
+    pub fn css(&self, py: Python<'_>, css: &str, include_scripts: bool) -> PyResult<Vec<Element>> {
+        self.select(py, css, None, include_scripts)
+    }
+
+    /// Select all elements matching a pre-compiled `CssSelector`.
+    ///
+    ///     sel = CssSelector("a[href]")
+    ///     links = doc.select_compiled(sel)
+    pub fn select_compiled(
+        &self,
+        py: Python<'_>,
+        selector: &CssSelector,
+    ) -> PyResult<Vec<Element>> {
+        self.select_detached(py, &selector.selector)
+    }
+
+    /// Return the first element matching a pre-compiled `CssSelector`, or None.
+    pub fn find_compiled(&self, selector: &CssSelector) -> PyResult<Option<Element>> {
+        let tree = self.tree()?;
+        let html = tree.lock();
+        Ok(select_elements(&html, &selector.selector)
+            .next()
+            .map(|el| Element::from_ref(tree, el)))
     }
 
     /// Evaluate an XPath expression against the whole document.
     ///
     /// The expression must return element nodes; attribute/text results are not supported.
+    /// The XPath tree is built on the first call, so CSS-only usage never pays for it.
     pub fn xpath(&self, expr: &str) -> PyResult<Vec<Element>> {
-        let document = self.xpath_package.as_document();
-        evaluate_xpath_elements(document.root().into(), expr)
+        self.xpath_limit(expr, usize::MAX)
     }
 
     /// Return the first matching element for an XPath expression, or None.
+    ///
+    /// Only the first match is snapshotted.
     pub fn xpath_first(&self, expr: &str) -> PyResult<Option<Element>> {
-        Ok(self.xpath(expr)?.into_iter().next())
+        Ok(self.xpath_limit(expr, 1)?.into_iter().next())
     }
 
     /// Explicitly release parsed DOMs to free memory early.
     ///
-    /// After calling, the document is reset to an empty state; selectors will
-    /// return no results. Safe to call multiple times; it also runs when the
-    /// Document is dropped.
+    /// After calling, methods that read the document raise
+    /// `ValueError("Document is closed")`. Elements selected before closing
+    /// remain readable.
+    /// Safe to call multiple times; it also runs when the Document is dropped.
     pub fn close(&mut self) {
         self.release_dom();
     }
 
+    /// True once `close()` has run, either directly or by leaving a `with` block.
+    #[getter]
+    pub fn closed(&self) -> bool {
+        self.tree.is_none()
+    }
+
     /// Support usage as a context manager to free resources on exit.
     fn __enter__(slf: PyRefMut<'_, Self>) -> PyRefMut<'_, Self> {
         slf
     }
 
     /// Support usage as a context manager to free resources on exit.
+    ///
+    /// Exceptions raised inside the `with` block are not suppressed.
     fn __exit__(
         mut self_: PyRefMut<'_, Self>,
         _exc_type: Option<Bound<'_, PyAny>>,
@@ -524,6 +5223,55 @@ impl Document {
         Ok(())
     }
 
+    /// `copy.copy(doc)`: an independent Document re-parsed from the same HTML,
+    /// base URL and parse mode.
+    fn __copy__(&self, py: Python<'_>) -> PyResult<Self> {
+        let html = &self.current_html()?;
+        let base_url = self.base_url_option.as_ref().map(Url::as_str);
+        Self::parse_detached(py, html, Some(html.len()), false, base_url, self.mode)
+    }
+
+    /// `copy.deepcopy(doc)`: same as `copy.copy`, since nothing is shared.
+    fn __deepcopy__(&self, py: Python<'_>, _memo: Bound<'_, PyAny>) -> PyResult<Self> {
+        self.__copy__(py)
+    }
+
+    /// Pickle support: a Document is pickled as its HTML and re-parsed on load.
+    ///
+    /// The base URL and parse mode are kept; the selector cache and the
+    /// XPath tree are rebuilt lazily as usual.
+    fn __reduce__<'py>(&self, py: Python<'py>) -> PyResult<(Bound<'py, PyAny>, DocumentState)> {
+        let from_state = py.get_type::<Document>().getattr("_from_state")?;
+        Ok((
+            from_state,
+            (
+                self.current_html()?.into_owned(),
+                self.base_url_option.as_ref().map(Url::to_string),
+                self.mode == ParseMode::Fragment,
+                self.mode == ParseMode::Xml,
+            ),
+        ))
+    }
+
+    /// Rebuild a pickled document; see `__reduce__`.
+    #[classmethod]
+    #[pyo3(signature = (html, base_url, fragment, xml=false))]
+    fn _from_state(
+        _cls: &Bound<'_, PyType>,
+        html: &str,
+        base_url: Option<&str>,
+        fragment: bool,
+        xml: bool,
+    ) -> PyResult<Self> {
+        let mode = if xml {
+            ParseMode::Xml
+        } else {
+            ParseMode::html(fragment)
+        };
+        // The HTML already passed the size check when it was first parsed.
+        Self::parse_with_limit(html, Some(html.len()), false, base_url, mode)
+    }
+
     fn __repr__(&self) -> String {
         let len = self.raw_html.len();
         format!("<Document len_html={}>", len)
@@ -536,10 +5284,218 @@ impl Drop for Document {
     }
 }
 
+/// Tokenizer sink behind `parse_streaming`: forwards tags and text to the
+/// Python callbacks and pauses the tokenizer once one of them asks to stop.
+struct StreamSink<'py> {
+    py: Python<'py>,
+    on_start: Option<Bound<'py, PyAny>>,
+    on_text: Option<Bound<'py, PyAny>>,
+    on_end: Option<Bound<'py, PyAny>>,
+    /// Character tokens arrive in chunks; they are joined until the next tag.
+    text: RefCell<String>,
+    stopped: Cell<bool>,
+    error: RefCell<Option<PyErr>>,
+}
+
+impl<'py> StreamSink<'py> {
+    /// Call `callback`, recording whether it returned `False` or raised.
+    fn call(&self, callback: &Option<Bound<'py, PyAny>>, args: impl PyCallArgs<'py>) {
+        let Some(callback) = callback else {
+            return;
+        };
+        match callback.call1(args) {
+            Ok(result) => {
+                if result.is(PyBool::new(self.py, false)) {
+                    self.stopped.set(true);
+                }
+            }
+            Err(err) => {
+                self.error.replace(Some(err));
+                self.stopped.set(true);
+            }
+        }
+    }
+
+    fn flush_text(&self) {
+        let text = self.text.take();
+        if !text.is_empty() {
+            self.call(&self.on_text, (text,));
+        }
+    }
+}
+
+impl TokenSink for StreamSink<'_> {
+    type Handle = ();
+
+    fn process_token(&self, token: Token, _line_number: u64) -> TokenSinkResult<()> {
+        let mut result = TokenSinkResult::Continue;
+        match token {
+            Token::CharacterTokens(text) => self.text.borrow_mut().push_str(&text),
+            Token::TagToken(tag) => {
+                self.flush_text();
+                if self.stopped.get() {
+                    return TokenSinkResult::Script(());
+                }
+                let name = tag.name.to_string();
+                match tag.kind {
+                    TagKind::StartTag => {
+                        // Without a tree builder the tokenizer has to be told
+                        // which elements hold raw text, as html5ever's does.
+                        result = match &*name {
+                            "script" => TokenSinkResult::RawData(RawKind::ScriptData),
+                            "style" | "xmp" | "iframe" | "noembed" | "noframes" => {
+                                TokenSinkResult::RawData(RawKind::Rawtext)
+                            }
+                            "title" | "textarea" => TokenSinkResult::RawData(RawKind::Rcdata),
+                            "plaintext" => TokenSinkResult::Plaintext,
+                            _ => TokenSinkResult::Continue,
+                        };
+                        let attrs: IndexMap<String, String> = tag
+                            .attrs
+                            .iter()
+                            .map(|attr| (attr.name.local.to_string(), attr.value.to_string()))
+                            .collect();
+                        self.call(&self.on_start, (name, attrs));
+                    }
+                    TagKind::EndTag => self.call(&self.on_end, (name,)),
+                }
+            }
+            // Comments and doctypes are not reported, but they still split
+            // text into separate nodes, as they do in the DOM.
+            Token::CommentToken(_) | Token::DoctypeToken(_) | Token::EOFToken => self.flush_text(),
+            _ => {}
+        }
+        if self.stopped.get() {
+            TokenSinkResult::Script(())
+        } else {
+            result
+        }
+    }
+}
+
+/// Tokenize `html` without building a DOM, calling `on_start(tag, attrs)`,
+/// `on_text(text)` and `on_end(tag)` as tags and text are encountered.
+///
+/// Any callback may be None. Returning `False` from a callback stops parsing
+/// right away; exceptions raised by callbacks propagate the same way. Returns
+/// True when the whole input was read and False when a callback stopped it.
+/// Self-closing and void tags only produce `on_start`; comments and doctypes
+/// are skipped (text on either side is reported separately), and tags are not
+/// balanced the way `Document` balances them.
+///
+///     def on_start(tag, attrs):
+///         if tag == "title":
+///             return False
+///
+///     parse_streaming(html, on_start=on_start)
+#[pyfunction]
+#[pyo3(signature = (
+    html,
+    on_start=None,
+    on_text=None,
+    on_end=None,
+    *,
+    max_size_bytes=None,
+    truncate_on_limit=false,
+))]
+fn parse_streaming<'py>(
+    py: Python<'py>,
+    html: &str,
+    on_start: Option<Bound<'py, PyAny>>,
+    on_text: Option<Bound<'py, PyAny>>,
+    on_end: Option<Bound<'py, PyAny>>,
+    max_size_bytes: Option<usize>,
+    truncate_on_limit: bool,
+) -> PyResult<bool> {
+    let html =
+        ensure_within_size_limit(html, effective_max_size(max_size_bytes), truncate_on_limit)?;
+    let sink = StreamSink {
+        py,
+        on_start,
+        on_text,
+        on_end,
+        text: RefCell::new(String::new()),
+        stopped: Cell::new(false),
+        error: RefCell::new(None),
+    };
+    let tokenizer = Tokenizer::new(sink, TokenizerOpts::default());
+    let input = BufferQueue::default();
+    input.push_back(html.as_ref().into());
+    if let TokenizerResult::Done = tokenizer.feed(&input) {
+        tokenizer.end();
+    }
+    if let Some(err) = tokenizer.sink.error.take() {
+        return Err(err);
+    }
+    Ok(!tokenizer.sink.stopped.get())
+}
+
+#[pyfunction]
+#[pyo3(signature = (
+    html,
+    *,
+    max_size_bytes=None,
+    truncate_on_limit=false,
+    base_url=None,
+    fragment=false,
+))]
+fn parse(
+    py: Python<'_>,
+    html: &str,
+    max_size_bytes: Option<usize>,
+    truncate_on_limit: bool,
+    base_url: Option<&str>,
+    fragment: bool,
+) -> PyResult<Document> {
+    Document::parse_detached(
+        py,
+        html,
+        max_size_bytes,
+        truncate_on_limit,
+        base_url,
+        ParseMode::html(fragment),
+    )
+}
+
+/// Parse many HTML strings at once, in parallel and without holding the GIL.
+///
+///     docs = parse_many(pages)
+///
+/// Options apply to every document. If any input fails (for example by
+/// exceeding `max_size_bytes`), the first error is raised.
 #[pyfunction]
-#[pyo3(signature = (html, *, max_size_bytes=None, truncate_on_limit=false))]
-fn parse(html: &str, max_size_bytes: Option<usize>, truncate_on_limit: bool) -> PyResult<Document> {
-    Document::from_html(html, max_size_bytes, truncate_on_limit)
+#[pyo3(signature = (
+    htmls,
+    *,
+    max_size_bytes=None,
+    truncate_on_limit=false,
+    base_url=None,
+    fragment=false,
+))]
+fn parse_many(
+    py: Python<'_>,
+    htmls: Vec<String>,
+    max_size_bytes: Option<usize>,
+    truncate_on_limit: bool,
+    base_url: Option<&str>,
+    fragment: bool,
+) -> PyResult<Vec<Document>> {
+    let base_url = base_url.map(parse_base_url).transpose()?;
+    let parsed = py.detach(|| {
+        htmls
+            .par_iter()
+            .map(|html| {
+                ParsedHtml::parse(
+                    html,
+                    max_size_bytes,
+                    truncate_on_limit,
+                    base_url.clone(),
+                    ParseMode::html(fragment),
+                )
+            })
+            .collect::<PyResult<Vec<_>>>()
+    })?;
+    Ok(parsed.into_iter().map(Document::from_parsed).collect())
 }
 
 #[pyfunction]
@@ -552,8 +5508,14 @@ fn select(
     truncate_on_limit: bool,
 ) -> PyResult<Vec<Element>> {
     py.detach(|| {
-        let doc = Document::from_html(html, max_size_bytes, truncate_on_limit)?;
-        doc.select(css)
+        let doc = Document::parse_with_limit(
+            html,
+            max_size_bytes,
+            truncate_on_limit,
+            None,
+            ParseMode::Document,
+        )?;
+        doc.select_with(&parse_selector(css)?)
     })
 }
 
@@ -567,8 +5529,14 @@ fn select_first(
     truncate_on_limit: bool,
 ) -> PyResult<Option<Element>> {
     py.detach(|| {
-        let doc = Document::from_html(html, max_size_bytes, truncate_on_limit)?;
-        doc.select_first(css)
+        let doc = Document::parse_with_limit(
+            html,
+            max_size_bytes,
+            truncate_on_limit,
+            None,
+            ParseMode::Document,
+        )?;
+        doc.select_first(css, false)
     })
 }
 
@@ -582,8 +5550,14 @@ fn first(
     truncate_on_limit: bool,
 ) -> PyResult<Option<Element>> {
     py.detach(|| {
-        let doc = Document::from_html(html, max_size_bytes, truncate_on_limit)?;
-        doc.find(css)
+        let doc = Document::parse_with_limit(
+            html,
+            max_size_bytes,
+            truncate_on_limit,
+            None,
+            ParseMode::Document,
+        )?;
+        doc.select_first(css, false)
     })
 }
 
@@ -597,7 +5571,13 @@ fn xpath(
     truncate_on_limit: bool,
 ) -> PyResult<Vec<Element>> {
     py.detach(|| {
-        let doc = Document::from_html(html, max_size_bytes, truncate_on_limit)?;
+        let doc = Document::parse_with_limit(
+            html,
+            max_size_bytes,
+            truncate_on_limit,
+            None,
+            ParseMode::Document,
+        )?;
         doc.xpath(expr)
     })
 }
@@ -612,7 +5592,13 @@ fn xpath_first(
     truncate_on_limit: bool,
 ) -> PyResult<Option<Element>> {
     py.detach(|| {
-        let doc = Document::from_html(html, max_size_bytes, truncate_on_limit)?;
+        let doc = Document::parse_with_limit(
+            html,
+            max_size_bytes,
+            truncate_on_limit,
+            None,
+            ParseMode::Document,
+        )?;
         doc.xpath_first(expr)
     })
 }
@@ -620,21 +5606,39 @@ fn xpath_first(
 // Async versions using pyo3-async-runtimes
 
 #[pyfunction]
-#[pyo3(signature = (html, css, *, max_size_bytes=None, truncate_on_limit=false))]
+#[pyo3(signature = (
+    html,
+    css,
+    *,
+    max_size_bytes=None,
+    truncate_on_limit=false,
+    include_scripts=false,
+))]
 fn select_async(
     py: Python<'_>,
     html: String,
     css: String,
     max_size_bytes: Option<usize>,
     truncate_on_limit: bool,
+    include_scripts: bool,
 ) -> PyResult<Bound<'_, PyAny>> {
     let locals = pyo3_async_runtimes::TaskLocals::with_running_loop(py)?.copy_context(py)?;
     pyo3_async_runtimes::tokio::future_into_py_with_locals(py, locals, async move {
         tokio::task::spawn_blocking(move || {
             Python::attach(|py| {
                 py.detach(|| {
-                    let doc = Document::from_html(&html, max_size_bytes, truncate_on_limit)?;
-                    doc.select(&css)
+                    let doc = Document::parse_with_limit(
+                        &html,
+                        max_size_bytes,
+                        truncate_on_limit,
+                        None,
+                        ParseMode::Document,
+                    )?;
+                    Ok(doc
+                        .select_with(&parse_selector(&css)?)?
+                        .into_iter()
+                        .map(|el| el.including_scripts(include_scripts))
+                        .collect::<Vec<_>>())
                 })
             })
         })
@@ -644,21 +5648,35 @@ fn select_async(
 }
 
 #[pyfunction]
-#[pyo3(signature = (html, css, *, max_size_bytes=None, truncate_on_limit=false))]
+#[pyo3(signature = (
+    html,
+    css,
+    *,
+    max_size_bytes=None,
+    truncate_on_limit=false,
+    include_scripts=false,
+))]
 fn select_first_async(
     py: Python<'_>,
     html: String,
     css: String,
     max_size_bytes: Option<usize>,
     truncate_on_limit: bool,
+    include_scripts: bool,
 ) -> PyResult<Bound<'_, PyAny>> {
     let locals = pyo3_async_runtimes::TaskLocals::with_running_loop(py)?.copy_context(py)?;
     pyo3_async_runtimes::tokio::future_into_py_with_locals(py, locals, async move {
         tokio::task::spawn_blocking(move || {
             Python::attach(|py| {
                 py.detach(|| {
-                    let doc = Document::from_html(&html, max_size_bytes, truncate_on_limit)?;
-                    doc.select_first(&css)
+                    let doc = Document::parse_with_limit(
+                        &html,
+                        max_size_bytes,
+                        truncate_on_limit,
+                        None,
+                        ParseMode::Document,
+                    )?;
+                    doc.select_first(&css, include_scripts)
                 })
             })
         })
@@ -681,8 +5699,14 @@ fn first_async(
         tokio::task::spawn_blocking(move || {
             Python::attach(|py| {
                 py.detach(|| {
-                    let doc = Document::from_html(&html, max_size_bytes, truncate_on_limit)?;
-                    doc.find(&css)
+                    let doc = Document::parse_with_limit(
+                        &html,
+                        max_size_bytes,
+                        truncate_on_limit,
+                        None,
+                        ParseMode::Document,
+                    )?;
+                    doc.select_first(&css, false)
                 })
             })
         })
@@ -705,7 +5729,13 @@ fn xpath_async(
         tokio::task::spawn_blocking(move || {
             Python::attach(|py| {
                 py.detach(|| {
-                    let doc = Document::from_html(&html, max_size_bytes, truncate_on_limit)?;
+                    let doc = Document::parse_with_limit(
+                        &html,
+                        max_size_bytes,
+                        truncate_on_limit,
+                        None,
+                        ParseMode::Document,
+                    )?;
                     doc.xpath(&expr)
                 })
             })
@@ -729,7 +5759,13 @@ fn xpath_first_async(
         tokio::task::spawn_blocking(move || {
             Python::attach(|py| {
                 py.detach(|| {
-                    let doc = Document::from_html(&html, max_size_bytes, truncate_on_limit)?;
+                    let doc = Document::parse_with_limit(
+                        &html,
+                        max_size_bytes,
+                        truncate_on_limit,
+                        None,
+                        ParseMode::Document,
+                    )?;
                     doc.xpath_first(&expr)
                 })
             })
@@ -740,12 +5776,18 @@ fn xpath_first_async(
 }
 
 #[pyfunction]
-#[pyo3(signature = (html, css))]
-fn _select_fragment_async(py: Python<'_>, html: String, css: String) -> PyResult<Bound<'_, PyAny>> {
+#[pyo3(signature = (element, css, *, include_scripts=false))]
+fn _element_select_async<'py>(
+    py: Python<'py>,
+    element: PyRef<'py, Element>,
+    css: String,
+    include_scripts: bool,
+) -> PyResult<Bound<'py, PyAny>> {
+    let element = element.clone();
     let locals = pyo3_async_runtimes::TaskLocals::with_running_loop(py)?.copy_context(py)?;
     pyo3_async_runtimes::tokio::future_into_py_with_locals(py, locals, async move {
         tokio::task::spawn_blocking(move || {
-            Python::attach(|py| py.detach(|| select_fragment(&html, &css)))
+            Python::attach(|py| py.detach(|| element.select(&css, include_scripts)))
         })
         .await
         .map_err(|e| PyValueError::new_err(format!("Task join error: {e}")))?
@@ -753,21 +5795,18 @@ fn _select_fragment_async(py: Python<'_>, html: String, css: String) -> PyResult
 }
 
 #[pyfunction]
-#[pyo3(signature = (html, css))]
-fn _select_first_fragment_async(
-    py: Python<'_>,
-    html: String,
+#[pyo3(signature = (element, css, *, include_scripts=false))]
+fn _element_select_first_async<'py>(
+    py: Python<'py>,
+    element: PyRef<'py, Element>,
     css: String,
-) -> PyResult<Bound<'_, PyAny>> {
+    include_scripts: bool,
+) -> PyResult<Bound<'py, PyAny>> {
+    let element = element.clone();
     let locals = pyo3_async_runtimes::TaskLocals::with_running_loop(py)?.copy_context(py)?;
     pyo3_async_runtimes::tokio::future_into_py_with_locals(py, locals, async move {
         tokio::task::spawn_blocking(move || {
-            Python::attach(|py| {
-                py.detach(|| {
-                    let elements = select_fragment(&html, &css)?;
-                    Ok(elements.into_iter().next())
-                })
-            })
+            Python::attach(|py| py.detach(|| element.select_first(&css, include_scripts)))
         })
         .await
         .map_err(|e| PyValueError::new_err(format!("Task join error: {e}")))?
@@ -780,7 +5819,7 @@ fn _xpath_fragment_async(py: Python<'_>, html: String, expr: String) -> PyResult
     let locals = pyo3_async_runtimes::TaskLocals::with_running_loop(py)?.copy_context(py)?;
     pyo3_async_runtimes::tokio::future_into_py_with_locals(py, locals, async move {
         tokio::task::spawn_blocking(move || {
-            Python::attach(|py| py.detach(|| evaluate_fragment_xpath(&html, &expr)))
+            Python::attach(|py| py.detach(|| evaluate_fragment_xpath(&html, &expr, usize::MAX)))
         })
         .await
         .map_err(|e| PyValueError::new_err(format!("Task join error: {e}")))?
@@ -799,7 +5838,7 @@ fn _xpath_first_fragment_async(
         tokio::task::spawn_blocking(move || {
             Python::attach(|py| {
                 py.detach(|| {
-                    let elements = evaluate_fragment_xpath(&html, &expr)?;
+                    let elements = evaluate_fragment_xpath(&html, &expr, 1)?;
                     Ok(elements.into_iter().next())
                 })
             })
@@ -809,15 +5848,156 @@ fn _xpath_first_fragment_async(
     })
 }
 
+/// What changed between two documents, as a line diff of their `to_text()`.
+///
+/// Returns one dict per changed run of lines, in order: `{"op": "added",
+/// "old": None, "new": text}`, `{"op": "removed", "old": text, "new": None}` or
+/// `{"op": "changed", "old": text, "new": text}`, where each text is the run's
+/// lines joined by newlines. Blank lines are ignored and unchanged lines are
+/// not reported, so identical pages give `[]`. With `css`, only the text of
+/// the matching elements is compared, which keeps navigation or ad churn out
+/// of the result.
+///
+///     for change in diff(yesterday, today, css=".content"):
+///         print(change["op"], change["new"])
+#[pyfunction]
+#[pyo3(signature = (old, new, css=None))]
+fn diff(
+    py: Python<'_>,
+    old: PyRef<'_, Document>,
+    new: PyRef<'_, Document>,
+    css: Option<&str>,
+) -> PyResult<Vec<Py<PyDict>>> {
+    let old_lines = old.text_lines(css)?;
+    let new_lines = new.text_lines(css)?;
+    let ops = py
+        .detach(|| similar::capture_diff_slices(similar::Algorithm::Myers, &old_lines, &new_lines));
+    let old_text = |range: std::ops::Range<usize>| Some(old_lines[range].join("\n"));
+    let new_text = |range: std::ops::Range<usize>| Some(new_lines[range].join("\n"));
+    let mut changes = Vec::new();
+    for op in &ops {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        let (kind, old, new) = match tag {
+            similar::DiffTag::Equal => continue,
+            similar::DiffTag::Insert => ("added", None, new_text(new_range)),
+            similar::DiffTag::Delete => ("removed", old_text(old_range), None),
+            similar::DiffTag::Replace => ("changed", old_text(old_range), new_text(new_range)),
+        };
+        let change = PyDict::new(py);
+        change.set_item("op", kind)?;
+        change.set_item("old", old)?;
+        change.set_item("new", new)?;
+        changes.push(change.unbind());
+    }
+    Ok(changes)
+}
+
+/// Escape `ident` for use as a CSS identifier, like the browser's
+/// `CSS.escape()`, so scraped ids and classes can be put into selectors.
+///
+///     doc.select_first(f"#{escape_css(item_id)}")
+#[pyfunction]
+fn escape_css(ident: &str) -> String {
+    escape_css_identifier(ident)
+}
+
+/// Set how many characters of text `repr(element)` shows before cutting it
+/// off with `...` (40 by default). The setting is process-wide.
+///
+///     set_repr_text_width(120)
+#[pyfunction]
+fn set_repr_text_width(width: usize) {
+    REPR_TEXT_WIDTH.store(width, Ordering::Relaxed);
+}
+
+/// Fetch `url` with a blocking HTTP client and parse the response body.
+///
+/// Redirects are followed and the final URL becomes the document's
+/// `base_url`. The body is decoded like `Document.from_bytes`, with the
+/// `Content-Type` charset (when it names a known encoding) taking the place of
+/// the `encoding` argument. Connection failures, timeouts and 4xx/5xx statuses
+/// raise OSError; the GIL is released for the whole request.
+///
+/// Only available when the extension is built with the `network` feature.
+///
+///     doc = fetch("https://example.com/", headers={"User-Agent": "my-bot"})
+#[cfg(feature = "network")]
+#[pyfunction]
+#[pyo3(signature = (
+    url,
+    *,
+    timeout=30.0,
+    headers=None,
+    max_size_bytes=None,
+    truncate_on_limit=false,
+))]
+fn fetch(
+    py: Python<'_>,
+    url: &str,
+    timeout: f64,
+    headers: Option<IndexMap<String, String>>,
+    max_size_bytes: Option<usize>,
+    truncate_on_limit: bool,
+) -> PyResult<Document> {
+    let timeout = std::time::Duration::try_from_secs_f64(timeout)
+        .map_err(|e| PyValueError::new_err(format!("Invalid timeout {timeout}: {e}")))?;
+    let parsed = py.detach(|| {
+        let request_error =
+            |e: reqwest::Error| PyOSError::new_err(format!("Failed to fetch {url:?}: {e}"));
+        let client = reqwest::blocking::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(request_error)?;
+        let mut request = client.get(url);
+        for (name, value) in headers.iter().flatten() {
+            request = request.header(name, value);
+        }
+        let response = request
+            .send()
+            .and_then(reqwest::blocking::Response::error_for_status)
+            .map_err(request_error)?;
+        let base_url = response.url().clone();
+        let charset = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(content_type_charset);
+        let body = response.bytes().map_err(request_error)?;
+        let html = decode_html_bytes(&body, charset.as_deref())?;
+        ParsedHtml::parse(
+            &html,
+            max_size_bytes,
+            truncate_on_limit,
+            Some(base_url),
+            ParseMode::Document,
+        )
+    })?;
+    Ok(Document::from_parsed(parsed))
+}
+
 /// Top-level module initializer.
 #[pymodule(gil_used = false)]
 fn scraper_rs(_py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
     // Classes
     m.add_class::<Document>()?;
     m.add_class::<Element>()?;
+    m.add_class::<CssSelector>()?;
+    m.add_class::<SelectIter>()?;
+    m.add_class::<TextIter>()?;
+
+    // Exceptions
+    m.add("SelectorError", m.py().get_type::<SelectorError>())?;
+    m.add("ParseError", m.py().get_type::<ParseError>())?;
 
     // Top-level functions
     m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_many, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_streaming, m)?)?;
+    m.add_function(wrap_pyfunction!(diff, m)?)?;
+    m.add_function(wrap_pyfunction!(escape_css, m)?)?;
+    m.add_function(wrap_pyfunction!(set_repr_text_width, m)?)?;
+    #[cfg(feature = "network")]
+    m.add_function(wrap_pyfunction!(fetch, m)?)?;
     m.add_function(wrap_pyfunction!(select, m)?)?;
     m.add_function(wrap_pyfunction!(select_first, m)?)?;
     m.add_function(wrap_pyfunction!(first, m)?)?;
@@ -830,8 +6010,8 @@ fn scraper_rs(_py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(first_async, m)?)?;
     m.add_function(wrap_pyfunction!(xpath_async, m)?)?;
     m.add_function(wrap_pyfunction!(xpath_first_async, m)?)?;
-    m.add_function(wrap_pyfunction!(_select_fragment_async, m)?)?;
-    m.add_function(wrap_pyfunction!(_select_first_fragment_async, m)?)?;
+    m.add_function(wrap_pyfunction!(_element_select_async, m)?)?;
+    m.add_function(wrap_pyfunction!(_element_select_first_async, m)?)?;
     m.add_function(wrap_pyfunction!(_xpath_fragment_async, m)?)?;
     m.add_function(wrap_pyfunction!(_xpath_first_fragment_async, m)?)?;
 
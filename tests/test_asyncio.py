@@ -257,3 +257,63 @@ async def test_async_nested_selectors(sample_html: str) -> None:
     assert first_link is not None
     assert first_link.text == "First"
     assert first_link.attr("href") == "/a"
+
+
+@pytest.mark.asyncio
+async def test_async_beautifulsoup_aliases(sample_html: str) -> None:
+    doc = await async_scraper.parse(sample_html)
+    items = await doc.find_all(".item")
+    link = await items[1].select_one("a")
+
+    assert len(items) == 2
+    assert (await doc.select_one("a")).text == "First"
+    assert link is not None and link.text == "Second"
+    assert [el.text for el in await items[0].find_all("a")] == ["First"]
+
+
+@pytest.mark.asyncio
+async def test_async_element_navigation(sample_html: str) -> None:
+    """Test tree navigation helpers on AsyncElement."""
+    items = await async_scraper.select(sample_html, ".item")
+
+    assert isinstance(items[0].parent(), async_scraper.AsyncElement)
+    assert items[0].parent().tag == "body"
+    assert [child.tag for child in items[0].children()] == ["a"]
+    assert [el.tag for el in items[0].descendants()] == ["a"]
+    assert items[0].next_sibling().attr("data-id") == "2"
+    assert items[1].previous_sibling().attr("data-id") == "1"
+    assert items[1].next_sibling() is None
+
+
+@pytest.mark.asyncio
+async def test_async_element_mapping_access(sample_html: str) -> None:
+    """Test dict-style attribute access on AsyncElement."""
+    item = await async_scraper.select_first(sample_html, ".item")
+
+    assert item["data-id"] == "1"
+    assert "class" in item
+    assert dict(item) == item.attrs
+    with pytest.raises(KeyError):
+        item["missing"]
+
+
+@pytest.mark.asyncio
+async def test_async_select_include_scripts() -> None:
+    """Test include_scripts on the AsyncDocument and AsyncElement select family."""
+    doc = await async_scraper.parse(
+        "<section><div>Hi<script>x = 1</script></div></section>"
+    )
+
+    assert (await doc.select("div"))[0].text == "Hi"
+    assert (await doc.select("div", include_scripts=True))[0].text == "Hi x = 1"
+    assert (await doc.find("div", include_scripts=True)).text == "Hi x = 1"
+    assert (await doc.select_first("div", include_scripts=True)).text == "Hi x = 1"
+    assert (await doc.select_one("div", include_scripts=True)).text == "Hi x = 1"
+    assert (await doc.find_all("div", include_scripts=True))[0].text == "Hi x = 1"
+    assert (await doc.css("div", include_scripts=True))[0].text == "Hi x = 1"
+    assert (await doc.find("div")).text == "Hi"
+
+    section = await doc.find("section")
+    assert (await section.select("div", include_scripts=True))[0].text == "Hi x = 1"
+    assert (await section.find("div", include_scripts=True)).text == "Hi x = 1"
+    assert (await section.find("div")).text == "Hi"
@@ -1,14 +1,34 @@
+import copy
+import csv
+import gzip
 import importlib.metadata
+import io
+import json
+import pathlib
+import pickle
+import threading
+import zlib
+from concurrent.futures import ThreadPoolExecutor
+from http.server import BaseHTTPRequestHandler, HTTPServer
 
 import pytest
 
+import scraper_rs
 from scraper_rs import (
+    CssSelector,
     Document,
+    ParseError,
+    SelectorError,
     __version__,
+    diff,
+    escape_css,
     first,
     parse,
+    parse_many,
+    parse_streaming,
     select,
     select_first,
+    set_repr_text_width,
     xpath,
     xpath_first,
 )
@@ -50,7 +70,8 @@ def test_select_and_element_helpers(sample_html: str) -> None:
     assert first_item.get("missing", "fallback") == "fallback"
     assert first_item.attrs["class"] == "item"
     assert first_item.attrs["data-id"] == "1"
-    assert "<Element tag='div' text=First>" in repr(first_item)
+    assert "<Element tag='div' class=\"item\" text=First>" in repr(first_item)
+    assert repr(first_item.find("a")) == "<Element tag='a' text=First>"
 
     expected_dict = {
         "tag": "div",
@@ -61,6 +82,813 @@ def test_select_and_element_helpers(sample_html: str) -> None:
     assert first_item.to_dict() == expected_dict
 
 
+def test_attrs_keep_source_order() -> None:
+    html = '<a zeta="1" href="/x" alpha="2" data-k="3" id="link">x</a>'
+    doc = Document(html)
+    expected = ["zeta", "href", "alpha", "data-k", "id"]
+
+    for link in (doc.find("a"), doc.xpath_first("//a")):
+        assert list(link.attrs) == expected
+        assert link.keys() == expected
+        assert list(link.to_dict()["attrs"]) == expected
+    assert doc.find("a").outer_html == html
+
+
+def test_element_mapping_access() -> None:
+    doc = Document('<a href="/x" id="link" data-n="1">x</a>')
+
+    for link in (doc.find("a"), doc.xpath_first("//a")):
+        assert link["href"] == "/x"
+        with pytest.raises(KeyError):
+            link["missing"]
+        assert "href" in link
+        assert "missing" not in link
+        assert sorted(link) == ["data-n", "href", "id"]
+        assert dict(link) == link.attrs
+        assert dict(link.items()) == link.attrs
+        assert list(zip(link.keys(), link.values())) == link.items()
+        assert bool(link)
+
+    assert dict(Document("<p>x</p>").find("p")) == {}
+
+
+def test_element_to_json_and_select_json() -> None:
+    html = """<p data-q='say "hi"' id="p1">Line "one"\n<b>two</b></p><p>x</p>"""
+    doc = Document(html)
+    first_p = doc.find("p")
+
+    data = json.loads(first_p.to_json())
+    assert data == {
+        "tag": "p",
+        "text": 'Line "one" two',
+        "html": 'Line "one"\n<b>two</b>',
+        "attrs": {"data-q": 'say "hi"', "id": "p1"},
+    }
+    assert list(data["attrs"]) == ["data-q", "id"]
+    assert data == first_p.to_dict()
+
+    array = json.loads(doc.select_json("p"))
+    assert array == [el.to_dict() for el in doc.select("p")]
+    assert doc.select_json("table") == "[]"
+    assert json.loads(doc.xpath_first("//b").to_json())["text"] == "two"
+
+
+def test_element_equality_and_hash() -> None:
+    doc = Document('<ul><li class="a">x</li><li class="a">x</li><li>y</li></ul>')
+    first, second, third = doc.select("li")
+
+    assert first == second
+    assert first != third
+    assert hash(first) == hash(second)
+    assert first == doc.xpath("//li")[0]
+    assert first == Document('<li class="a">x</li>').find("li")
+    assert len({*doc.select("li"), *doc.select("li.a")}) == 2
+    assert first != "x"
+
+
+def test_copy_and_deepcopy() -> None:
+    doc = Document('<base href="docs/"><a href="x">A</a>', base_url="https://e.com/")
+
+    for clone in (copy.copy(doc), copy.deepcopy(doc)):
+        assert clone is not doc
+        assert clone.html == doc.html
+        assert clone.base_url == "https://e.com/docs/"
+        assert clone.find("a").abs_attr("href") == "https://e.com/docs/x"
+        clone.close()
+        assert not doc.closed
+
+    fragment = copy.deepcopy(Document.parse_fragment("<li>a</li>"))
+    assert fragment.find("html") is None
+
+    link = doc.find("a")
+    assert copy.copy(link) == link
+    assert copy.copy(link).parent().tag == "body"
+    assert copy.deepcopy(link) == link
+    assert copy.deepcopy(link).parent() is None
+    assert copy.deepcopy(link).abs_attr("href") == "https://e.com/docs/x"
+    assert copy.deepcopy([link])[0].text == "A"
+
+
+def test_element_deepcopy_is_independent_of_the_document() -> None:
+    doc = Document('<div><a href="/x" class="k">A</a></div>')
+    link = doc.find("a")
+    clone = copy.deepcopy(link)
+
+    with pytest.raises(ValueError, match="snapshot"):
+        clone.set_attr("href", "/y")
+    with pytest.raises(ValueError, match="snapshot"):
+        clone.remove()
+    assert link.attr("href") == "/x"
+    assert doc.find("a") is not None
+
+    link.set_attr("href", "/z")
+    link.remove()
+    assert clone.attr("href") == "/x"
+    assert clone.outer_html == '<a href="/x" class="k">A</a>'
+    assert doc.find("a") is None
+
+
+def test_document_pickle_roundtrip(sample_html: str) -> None:
+    doc = Document(sample_html, base_url="https://example.com/")
+    doc.select("div.item")
+
+    restored = pickle.loads(pickle.dumps(doc))
+    assert restored.html == doc.html
+    assert restored.base_url == "https://example.com/"
+    assert restored.select("div.item") == doc.select("div.item")
+    assert restored.xpath_first("//a").abs_attr("href") == "https://example.com/a"
+
+    fragment = pickle.loads(pickle.dumps(Document.parse_fragment("<li>a</li>")))
+    assert fragment.find("li").parent() is None
+
+    based = Document('<base href="docs/">', base_url="https://example.com/")
+    assert pickle.loads(pickle.dumps(based)).base_url == "https://example.com/docs/"
+
+
+def test_element_pickle_roundtrip() -> None:
+    doc = Document(
+        '<div id="card" class="a b"><a href="/x">Link</a></div>',
+        base_url="https://example.com/",
+    )
+
+    for element in (doc.find("div"), doc.xpath_first("//div")):
+        restored = pickle.loads(pickle.dumps(element))
+        assert restored == element
+        assert restored.outer_html == element.outer_html
+        assert restored.attrs == {"id": "card", "class": "a b"}
+        assert restored.classes() == ["a", "b"]
+        assert restored.find("a").abs_attr("href") == "https://example.com/x"
+        assert restored.parent() is None
+
+
+def test_element_classes() -> None:
+    doc = Document('<p class=" lead  note\tlead ">x</p><p class="">y</p><p>z</p>')
+    lead, empty, missing = doc.select("p")
+
+    assert lead.classes() == ["lead", "note"]
+    assert lead.has_class("note")
+    assert not lead.has_class("lea")
+    assert empty.classes() == []
+    assert not empty.has_class("")
+    assert missing.classes() == []
+    assert not missing.has_class("lead")
+    assert doc.xpath_first("//p").classes() == ["lead", "note"]
+
+
+def test_element_id_and_document_by_id() -> None:
+    html = """
+    <div id="main">main</div>
+    <div id="123">digits</div>
+    <div id="a.b:c">punctuation</div>
+    <div id="-">dash</div>
+    <p>no id</p>
+    """
+    doc = Document(html)
+
+    assert doc.find("div").id == "main"
+    assert doc.find("p").id is None
+    assert doc.by_id("main").text == "main"
+    assert doc.by_id("123").text == "digits"
+    assert doc.by_id("a.b:c").text == "punctuation"
+    assert doc.by_id("-").text == "dash"
+    assert doc.by_id("missing") is None
+    assert doc.by_id("") is None
+
+
+def test_document_by_class_and_by_tag() -> None:
+    doc = Document(
+        '<div class="card md:flex">a</div><p class="w-1/2 card">b</p>'
+        '<span class="cards">c</span>'
+    )
+
+    assert [el.text for el in doc.by_class("card")] == ["a", "b"]
+    assert [el.text for el in doc.by_class("md:flex")] == ["a"]
+    assert [el.text for el in doc.by_class("w-1/2")] == ["b"]
+    assert doc.by_class("") == []
+    assert [el.tag for el in doc.by_tag("p")] == ["p"]
+    assert [el.tag for el in doc.by_tag("P")] == ["p"]
+    assert doc.by_tag("table") == []
+
+
+def test_find_by_attrs() -> None:
+    doc = Document(
+        '<a class="x y" href="/1">1</a><a class="xy" href="/2">2</a>'
+        '<button type="submit" data-k="a\'b">go</button><span class="x">s</span>'
+    )
+
+    assert [el.text for el in doc.find_by_attrs("a", {"class": "x"})] == ["1"]
+    assert [el.text for el in doc.find_by_attrs(attrs={"class": "x"})] == ["1", "s"]
+    assert [el.text for el in doc.find_by_attrs("A", {"href": "/2"})] == ["2"]
+    assert doc.find_by_attrs("a", {"href": "/"}) == []
+    assert [el.text for el in doc.find_by_attrs(attrs={"data-k": "a'b"})] == ["go"]
+    assert len(doc.find_by_attrs("a")) == 2
+
+
+def test_select_attr_regex() -> None:
+    doc = Document(
+        '<a href="/product/12">a</a><a href="/product/x">b</a>'
+        '<a href="/en/product/7">c</a><img src="/product/9.png"><a>none</a>'
+    )
+
+    found = doc.select_attr_regex("a", "href", r"^/product/\d+$")
+    assert [el.text for el in found] == ["a"]
+    found = doc.select_attr_regex(None, "href", r"product/\d")
+    assert [el.text for el in found] == ["a", "c"]
+    assert [el.tag for el in doc.select_attr_regex(None, "src", r"\.png$")] == ["img"]
+
+    with pytest.raises(ValueError, match="Invalid regex"):
+        doc.select_attr_regex("a", "href", "(")
+
+
+def test_having_attr() -> None:
+    doc = Document(
+        '<img alt="Logo"><img alt=""><a href="/" alt=" ">x</a><p>none</p>'
+        '<div data-id="1"><span data-id="">y</span></div>'
+    )
+
+    assert [el.tag for el in doc.having_attr("alt")] == ["img", "img", "a"]
+    assert [el.attr("alt") for el in doc.having_attr("alt", non_empty=True)] == [
+        "Logo"
+    ]
+    assert [el.tag for el in doc.having_attr("data-id")] == ["div", "span"]
+    assert [el.tag for el in doc.having_attr("data-id", True)] == ["div"]
+    assert doc.having_attr("title") == []
+
+
+def test_extract_urls() -> None:
+    html = """
+    <link rel="stylesheet" href="/style.css">
+    <a href="/a">A</a><a href="/a">again</a><a href="">empty</a>
+    <img src="img.png" srcset="small.png 480w, large.png 2x,tiny.png">
+    <video poster="poster.jpg"><source src="clip.mp4"></video>
+    <div data-src="lazy.jpg"></div>
+    <form action="/submit"></form>
+    """
+    doc = Document(html)
+
+    assert doc.extract_urls() == [
+        "/style.css",
+        "/a",
+        "img.png",
+        "small.png",
+        "large.png",
+        "tiny.png",
+        "poster.jpg",
+        "clip.mp4",
+        "lazy.jpg",
+        "/submit",
+    ]
+    urls = doc.extract_urls("https://example.com/dir/")
+    assert urls[:4] == [
+        "https://example.com/style.css",
+        "https://example.com/a",
+        "https://example.com/dir/img.png",
+        "https://example.com/dir/small.png",
+    ]
+
+    based = Document('<base href="/sub/"><img src="x.png">')
+    assert based.extract_urls("https://example.com/") == [
+        "https://example.com/sub/",
+        "https://example.com/sub/x.png",
+    ]
+    with pytest.raises(ValueError, match="Invalid base_url"):
+        doc.extract_urls("not a url")
+
+
+def test_images() -> None:
+    html = """
+    <img src="/a.png" alt="A" width="10" height="20">
+    <img srcset="b-1x.png 1x, b-2x.png 2x" alt="">
+    <img>
+    """
+    doc = Document(html)
+
+    assert doc.images() == [
+        {"src": "/a.png", "alt": "A", "width": "10", "height": "20"},
+        {"src": "b-1x.png", "alt": ""},
+        {},
+    ]
+    resolved = doc.images("https://example.com/pics/")
+    assert [image.get("src") for image in resolved] == [
+        "https://example.com/a.png",
+        "https://example.com/pics/b-1x.png",
+        None,
+    ]
+
+
+def test_parse_streaming() -> None:
+    html = (
+        '<p class="x">Hi &amp; bye<br/></p>'
+        "<script>if (a < b) {}</script><div>rest</div>"
+    )
+    events: list[tuple] = []
+
+    completed = parse_streaming(
+        html,
+        on_start=lambda tag, attrs: events.append(("start", tag, attrs)),
+        on_text=lambda text: events.append(("text", text)),
+        on_end=lambda tag: events.append(("end", tag)),
+    )
+    assert completed is True
+    assert events == [
+        ("start", "p", {"class": "x"}),
+        ("text", "Hi & bye"),
+        ("start", "br", {}),
+        ("end", "p"),
+        ("start", "script", {}),
+        ("text", "if (a < b) {}"),
+        ("end", "script"),
+        ("start", "div", {}),
+        ("text", "rest"),
+        ("end", "div"),
+    ]
+
+    seen: list[str] = []
+
+    def on_start(tag: str, attrs: dict[str, str]) -> bool:
+        seen.append(tag)
+        return tag != "script"
+
+    assert parse_streaming(html, on_start) is False
+    assert seen == ["p", "br", "script"]
+
+    def boom(text: str) -> None:
+        raise RuntimeError("stop")
+
+    with pytest.raises(RuntimeError, match="stop"):
+        parse_streaming(html, on_text=boom)
+
+
+def test_parse_streaming_splits_text_at_comments_and_doctype() -> None:
+    texts: list[str] = []
+
+    parse_streaming("x<!DOCTYPE html><p>a<!--c-->b</p>tail", on_text=texts.append)
+
+    assert texts == ["x", "a", "b", "tail"]
+
+
+def test_strict_parsing() -> None:
+    good = "<!DOCTYPE html><html><head><title>t</title></head><body></body></html>"
+    assert Document(good, strict=True).title == "t"
+    assert Document.from_html(good, strict=True).title == "t"
+
+    bad = "<!DOCTYPE html>\n<p>one\n<div></span></div>"
+    assert Document(bad).find("div") is not None
+    with pytest.raises(ParseError, match="at line 3") as info:
+        Document(bad, strict=True)
+    assert isinstance(info.value, ValueError)
+
+
+def test_document_root(sample_html: str) -> None:
+    doc = Document(sample_html)
+    root = doc.root()
+    assert root.tag == "html"
+    assert [child.tag for child in root.children()] == ["head", "body"]
+    assert len(root.select("div.item")) == 2
+
+    single = Document.parse_fragment('<ul class="x"><li>a</li></ul>')
+    assert single.root().tag == "ul"
+    assert single.root().parent() is None
+    assert single.root().css_path() == "ul.x"
+    assert [el.tag for el in single.walk()] == ["ul", "li"]
+    assert single.find("li").parent().parent() is None
+    assert single.find("li").find_parents("*")[-1].tag == "ul"
+    fragment = Document.parse_fragment("<li>a</li><li>b</li>")
+    with pytest.raises(ValueError, match="found 2"):
+        fragment.root()
+    assert [el.tag for el in fragment.walk()] == ["li", "li"]
+    assert Document.parse_xml("<feed><entry/></feed>").root().tag == "feed"
+
+
+def test_document_comments() -> None:
+    doc = Document(
+        "<!-- top --><html><body><p>x<!--inner--></p>"
+        "<script>/* <!-- not a comment --> */</script></body></html><!-- end -->"
+    )
+    assert doc.comments() == [" top ", "inner", " end "]
+    assert Document("<p>none</p>").comments() == []
+
+
+def test_document_doctype() -> None:
+    assert Document("<!DOCTYPE HTML><p>x</p>").doctype == "html"
+    assert Document("<p>x</p>").doctype is None
+
+    legacy = Document(
+        '<!DOCTYPE html PUBLIC "-//W3C//DTD HTML 4.01//EN" '
+        '"http://www.w3.org/TR/html4/strict.dtd"><p>x</p>'
+    )
+    assert legacy.doctype == (
+        'html PUBLIC "-//W3C//DTD HTML 4.01//EN" '
+        '"http://www.w3.org/TR/html4/strict.dtd"'
+    )
+    assert legacy.quirks_mode == "no-quirks"
+
+
+def test_word_count_and_reading_time() -> None:
+    doc = Document(
+        "<html><head><title>Two words</title><style>p { x: y }</style></head>"
+        "<body><p>one  two\nthree</p><script>var a = 1;</script><p>four</p></body>"
+        "</html>"
+    )
+    assert doc.word_count() == len(doc.text.split()) == 6
+    assert doc.reading_time_minutes(3) == 2.0
+    assert doc.reading_time_minutes() == 6 / 200
+    with pytest.raises(ValueError):
+        doc.reading_time_minutes(0)
+
+
+def test_select_unique() -> None:
+    doc = Document(
+        '<a href="/a">Home</a><a href="/b">About</a><a href="/a">Start</a>'
+        '<a href="/c">Home</a><a>No link</a>'
+    )
+
+    by_href = doc.select_unique("a", "attr:href")
+    assert [el.text for el in by_href] == ["Home", "About", "Home"]
+    by_text = doc.select_unique("a", "text")
+    assert [el.attr("href") for el in by_text] == ["/a", "/b", "/a", None]
+    assert len(doc.select_unique("a", "tag")) == 1
+
+    with pytest.raises(ValueError, match="Invalid key"):
+        doc.select_unique("a", "href")
+
+
+def test_select_slice() -> None:
+    doc = Document("".join(f"<p>{i}</p>" for i in range(10)))
+
+    assert [el.text for el in doc.select_slice("p", 2, 3)] == ["2", "3", "4"]
+    assert [el.text for el in doc.select_slice("p", 8)] == ["8", "9"]
+    assert doc.select_slice("p", 20, 5) == []
+    assert doc.select_slice("p", 0, 0) == []
+    assert doc.select_slice("p", 0) == doc.select("p")
+
+
+def test_nth_and_last() -> None:
+    doc = Document("<ul><li>a</li><li>b</li><li>c</li></ul>")
+
+    assert doc.nth("li", 0).text == "a"
+    assert doc.nth("li", 2).text == "c"
+    assert doc.nth("li", -1).text == "c"
+    assert doc.nth("li", -3).text == "a"
+    assert doc.nth("li", 3) is None
+    assert doc.nth("li", -4) is None
+    assert doc.last("li").text == "c"
+    assert doc.last("p") is None
+
+
+def test_document_to_tree() -> None:
+    doc = Document.parse_fragment('<p class="x">Hi <b>there</b>!<!-- c --></p>')
+    tree = doc.to_tree()
+
+    assert tree["tag"] == "html"
+    (p,) = tree["children"]
+    assert p == {
+        "tag": "p",
+        "attrs": {"class": "x"},
+        "text": "Hi !",
+        "children": [
+            {"tag": "#text", "text": "Hi "},
+            {
+                "tag": "b",
+                "attrs": {},
+                "text": "there",
+                "children": [{"tag": "#text", "text": "there"}],
+            },
+            {"tag": "#text", "text": "!"},
+            {"tag": "#comment", "text": " c "},
+        ],
+    }
+    assert json.loads(json.dumps(tree)) == tree
+    full = Document("<title>t</title>").to_tree()
+    assert [child["tag"] for child in full["children"]] == ["head", "body"]
+
+
+def test_select_tags_filter() -> None:
+    doc = Document(
+        '<h1 class="headline">A</h1><div class="headline">B</div>'
+        '<h2 class="headline">C</h2><h3 class="headline">D</h3>'
+    )
+
+    found = doc.select(".headline", tags=["h1", "H2"])
+    assert [el.text for el in found] == ["A", "C"]
+    assert doc.select(".headline", tags=[]) == []
+    assert len(doc.select(".headline", tags=None)) == 4
+
+
+def test_opengraph() -> None:
+    doc = Document(
+        """
+        <meta property="og:title" content="Title">
+        <meta property="og:image" content="/first.png">
+        <meta property="og:image" content="/second.png">
+        <meta name="twitter:card" content="summary">
+        <meta property="twitter:site" content="@site">
+        <meta name="description" content="not a card">
+        <meta property="og:url">
+        """
+    )
+    assert doc.opengraph() == {
+        "og:title": "Title",
+        "og:image": "/first.png",
+        "twitter:card": "summary",
+        "twitter:site": "@site",
+    }
+    assert Document("<p>x</p>").opengraph() == {}
+
+
+def test_lang() -> None:
+    doc = Document(
+        '<html lang="en-US"><body><p>Hi <q lang="fr">Salut</q></p>'
+        '<div lang=""><span>?</span></div></body></html>'
+    )
+    assert doc.lang() == "en-US"
+    assert doc.find("p").lang() == "en-US"
+    assert doc.find("q").lang() == "fr"
+    assert doc.find("span").lang() is None
+
+    pragma = Document(
+        '<meta http-equiv="content-language" content="de, en"><p>Hallo</p>'
+    )
+    assert pragma.lang() == "de"
+    assert pragma.find("p").lang() == "de"
+    assert Document("<p>x</p>").lang() is None
+
+
+def test_canonical_url() -> None:
+    doc = Document(
+        '<link rel="stylesheet" href="/s.css">'
+        '<link rel="Canonical" href="/article">'
+        '<meta property="og:url" content="https://example.com/og">'
+    )
+    assert doc.canonical_url() == "/article"
+    assert doc.canonical_url("https://example.com/a?x=1") == (
+        "https://example.com/article"
+    )
+
+    og_only = Document('<meta property="og:url" content="https://example.com/og">')
+    assert og_only.canonical_url() == "https://example.com/og"
+    assert Document("<p>x</p>").canonical_url() is None
+
+
+def test_has_pseudo_class() -> None:
+    doc = Document(
+        '<div class="card"><p><img src="a.png"></p></div>'
+        '<div class="card"><p>text</p></div>'
+        "<ul><li>a</li><li>b</li></ul>"
+    )
+
+    assert [el.find("img") is not None for el in doc.select("div.card")] == [
+        True,
+        False,
+    ]
+    assert len(doc.select("div.card:has(img)")) == 1
+    assert doc.select("div.card:has(> img)") == []
+    assert len(doc.select("div.card:has(> p > img)")) == 1
+    assert [el.text for el in doc.select("li:has(+ li)")] == ["a"]
+    assert len(doc.select("div:not(:has(img))")) == 1
+    assert doc.find("div").matches(":has(img)")
+
+    with pytest.raises(SelectorError):
+        doc.select("p:contains(text)")
+
+
+def test_select_reaches_template_contents() -> None:
+    doc = Document(
+        '<template id="row"><div class="card"><a href="/x">In</a></div></template>'
+        "<p>Out</p>"
+    )
+    template = doc.by_id("row")
+
+    assert [el.text for el in doc.select(".card a")] == ["In"]
+    assert [el.text for el in template.select("a")] == ["In"]
+    assert [el.text for el in doc.xpath("//template//a")] == ["In"]
+    # Template contents live in their own fragment, so combinators do not
+    # cross the <template> boundary and the fragment's top elements have no
+    # parent.
+    assert doc.select("template .card") == []
+    assert doc.find(".card").parent() is None
+    assert template.children() == []
+    assert doc.text == "Out"
+
+
+def test_select_templates() -> None:
+    doc = Document(
+        '<div class="card">Live</div>'
+        '<template><ul class="list"><li class="card">A</li></ul></template>'
+        '<template><div class="card">B<template><b class="card">C</b></template>'
+        "</div></template>"
+    )
+
+    assert [el.text for el in doc.select_templates(".card")] == ["A", "B", "C"]
+    assert [el.text for el in doc.select_templates(".list > .card")] == ["A"]
+    assert doc.select_templates("template .card") == []
+    assert doc.select_templates("p") == []
+    with pytest.raises(SelectorError):
+        doc.select_templates("[")
+
+
+def test_emails_and_phones() -> None:
+    doc = Document(
+        """
+        <a href="mailto:Sales@Example.com?subject=Hi">Write us</a>
+        <a href="MAILTO:a@example.com,b@example.org">Both</a>
+        <a href="tel:+1-555-123-4567">Call</a>
+        <p>Or mail info@example.com. Sales: sales@example.com</p>
+        <p>Office: +44 20 7946 0958, fax (555) 765-4321.</p>
+        <p>Order 123456789 shipped on 2024-01-31 at 12.30.</p>
+        <script>var x = "hidden@example.com"; var t = "+1 555 000 0000";</script>
+        """
+    )
+
+    assert doc.emails() == [
+        "sales@example.com",
+        "a@example.com",
+        "b@example.org",
+        "info@example.com",
+    ]
+    assert doc.phones() == ["+15551234567", "+442079460958", "5557654321"]
+    assert Document("<p>nothing here</p>").emails() == []
+    assert Document("<p>nothing here</p>").phones() == []
+
+
+class _PageHandler(BaseHTTPRequestHandler):
+    def do_GET(self) -> None:
+        if self.path == "/old":
+            self.send_response(302)
+            self.send_header("Location", "/docs/page")
+            self.end_headers()
+            return
+        if self.path != "/docs/page":
+            self.send_error(404)
+            return
+        body = "<title>Привет</title><p>{}</p><a href='next'>n</a>".format(
+            self.headers.get("X-Token", "")
+        ).encode("windows-1251")
+        self.send_response(200)
+        self.send_header("Content-Type", "text/html; charset=windows-1251")
+        self.send_header("Content-Length", str(len(body)))
+        self.end_headers()
+        self.wfile.write(body)
+
+    def log_message(self, *args: object) -> None:
+        pass
+
+
+def test_fetch() -> None:
+    if not hasattr(scraper_rs, "fetch"):
+        pytest.skip("built without the network feature")
+
+    server = HTTPServer(("127.0.0.1", 0), _PageHandler)
+    threading.Thread(target=server.serve_forever, daemon=True).start()
+    root = f"http://127.0.0.1:{server.server_port}"
+    try:
+        doc = scraper_rs.fetch(f"{root}/old", headers={"X-Token": "secret"})
+        assert doc.title == "Привет"
+        assert doc.find("p").text == "secret"
+        assert doc.base_url == f"{root}/docs/page"
+        assert doc.find("a").abs_attr("href") == f"{root}/docs/next"
+
+        with pytest.raises(OSError, match="404"):
+            scraper_rs.fetch(f"{root}/missing")
+    finally:
+        server.shutdown()
+        server.server_close()
+
+
+def test_from_bytes_decompress() -> None:
+    html = "<title>Zipped</title><p>café</p>".encode()
+    raw_deflate = zlib.compressobj(wbits=-15)
+    raw = raw_deflate.compress(html) + raw_deflate.flush()
+
+    for data, method in [
+        (gzip.compress(html), "gzip"),
+        (gzip.compress(html), "auto"),
+        (zlib.compress(html), "deflate"),
+        (zlib.compress(html), "auto"),
+        (raw, "deflate"),
+        (html, "auto"),
+    ]:
+        doc = Document.from_bytes(data, decompress=method)
+        assert doc.title == "Zipped"
+        assert doc.find("p").text == "café"
+
+    with pytest.raises(ValueError, match="Unknown decompression"):
+        Document.from_bytes(html, decompress="zip")
+    with pytest.raises(ValueError, match="Failed to decompress gzip"):
+        Document.from_bytes(b"\x1f\x8bnot gzip", decompress="gzip")
+    brotli_hello = b"\x0b\x02\x80hello\x03"  # brotli.compress(b"hello")
+    assert Document.from_bytes(brotli_hello, decompress="br").text == "hello"
+    with pytest.raises(ValueError, match="Failed to decompress br"):
+        Document.from_bytes(b"\xff\xff\xff", decompress="br")
+    bomb = gzip.compress(b"a" * 10_000)
+    with pytest.raises(ValueError, match="too large"):
+        Document.from_bytes(bomb, max_size_bytes=100, decompress="gzip")
+    truncated = Document.from_bytes(
+        bomb, max_size_bytes=100, truncate_on_limit=True, decompress="gzip"
+    )
+    assert len(truncated.html) == 100
+
+
+def test_from_reader() -> None:
+    html = '<meta charset="windows-1252"><title>Caf\xe9</title>' + "<p>x</p>" * 20_000
+    doc = Document.from_reader(io.BytesIO(html.encode("cp1252")))
+    assert doc.title == "Café"
+    assert len(doc.select("p")) == 20_000
+
+    text_doc = Document.from_reader(
+        io.StringIO("<p>naïve</p>"), base_url="https://e.com/"
+    )
+    assert text_doc.find("p").text == "naïve"
+    assert text_doc.base_url == "https://e.com/"
+    zipped = gzip.GzipFile(fileobj=io.BytesIO(gzip.compress(b"<p>zipped</p>")))
+    assert Document.from_reader(zipped).text == "zipped"
+    sjis = io.BytesIO("<p>日本</p>".encode("shift_jis"))
+    assert Document.from_reader(sjis, "shift_jis").text == "日本"
+
+    big = io.BytesIO(b"a" * 1_000_000)
+    with pytest.raises(ValueError, match="too large"):
+        Document.from_reader(big, max_size_bytes=100)
+    assert big.tell() < 1_000_000
+    truncated = Document.from_reader(
+        io.BytesIO(b"a" * 1000), max_size_bytes=100, truncate_on_limit=True
+    )
+    assert len(truncated.html) == 100
+
+    class Broken:
+        def read(self, size: int) -> int:
+            return 42
+
+    with pytest.raises(TypeError, match="bytes or str"):
+        Document.from_reader(Broken())
+    with pytest.raises(AttributeError):
+        Document.from_reader(object())
+
+
+def test_first_helpers_match_full_selection() -> None:
+    html = "<div><p id='a'>1</p><p id='b'>2</p></div><div><p id='c'>3</p></div>"
+    doc = Document(html)
+
+    assert doc.select_first("p") == doc.select("p")[0]
+    assert doc.xpath_first("//p").id == doc.xpath("//p")[0].id == "a"
+    assert doc.xpath_first("//span") is None
+
+    snapshot = doc.xpath_first("//div")
+    assert snapshot.select_first("p").id == "a"
+    assert snapshot.xpath_first("//p").id == snapshot.xpath("//p")[0].id
+    assert snapshot.select_first("span") is None
+
+
+def test_diff_documents() -> None:
+    old = Document(
+        "<nav>Home</nav><div class='content'><p>Intro</p><p>Price: $10</p>"
+        "<p>Old note</p></div>"
+    )
+    new = Document(
+        "<nav>Home | Sale</nav><div class='content'><p>Intro</p><p>Price: $12</p>"
+        "<p>Shipping soon</p><p>New line</p></div>"
+    )
+
+    assert diff(old, old) == []
+    assert diff(old, new, css=".content") == [
+        {
+            "op": "changed",
+            "old": "Price: $10\nOld note",
+            "new": "Price: $12\nShipping soon\nNew line",
+        },
+    ]
+    changes = diff(old, new)
+    assert changes[0] == {"op": "changed", "old": "Home", "new": "Home | Sale"}
+
+    assert diff(Document("<p>a</p>"), Document("<p>a</p><p>b</p>")) == [
+        {"op": "added", "old": None, "new": "b"}
+    ]
+    assert diff(Document("<p>a</p><p>b</p>"), Document("<p>b</p>")) == [
+        {"op": "removed", "old": "a", "new": None}
+    ]
+
+
+def test_element_outer_html(sample_html: str) -> None:
+    doc = Document(sample_html)
+
+    item = doc.find(".item")
+    assert item is not None
+    assert (
+        item.outer_html == '<div class="item" data-id="1"><a href="/a">First</a></div>'
+    )
+    assert item.html in item.outer_html
+
+    form = Document('<form><img src="/x.png"><input disabled></form>')
+    assert form.find("img").outer_html == '<img src="/x.png">'
+    assert form.find("input").outer_html == '<input disabled="">'
+
+    xpath_item = doc.xpath_first("//div[@data-id='2']")
+    assert xpath_item is not None
+    assert xpath_item.outer_html == (
+        '<div class="item" data-id="2"><a href="/b">Second</a></div>'
+    )
+    assert form.xpath_first("//img").outer_html == '<img src="/x.png">'
+
+
 def test_document_size_limit(sample_html: str) -> None:
     tiny_limit = 10
     with pytest.raises(ValueError, match="too large"):
@@ -72,6 +900,13 @@ def test_document_size_limit(sample_html: str) -> None:
 
     with pytest.raises(ValueError):
         select(sample_html, "a[href]", max_size_bytes=tiny_limit)
+    with pytest.raises(ValueError, match="too large"):
+        parse(sample_html, max_size_bytes=tiny_limit)
+    with pytest.raises(ValueError, match="too large"):
+        Document.from_bytes(sample_html.encode(), max_size_bytes=tiny_limit)
+
+    truncated = parse(sample_html, max_size_bytes=tiny_limit, truncate_on_limit=True)
+    assert truncated.html == sample_html[:tiny_limit]
 
 
 def test_document_truncate_on_limit() -> None:
@@ -143,6 +978,1199 @@ def test_truncate_utf8_boundary() -> None:
     assert isinstance(text, str)
 
 
+def test_get_text_separator_and_strip() -> None:
+    html = """
+    <address>
+      <span>1 Main St</span>
+      <span>Springfield</span>
+    </address>
+    """
+    doc = Document(html)
+    address = doc.find("address")
+
+    assert address.get_text("\n", strip=True) == "1 Main St\nSpringfield"
+    assert address.get_text("|", strip=True) == "1 Main St|Springfield"
+    assert address.get_text("") == address.get_text(separator="", strip=False)
+    assert "\n" in address.get_text()
+    assert doc.get_text("\n", strip=True) == "1 Main St\nSpringfield"
+    assert doc.xpath_first("//address").get_text(",", strip=True) == (
+        "1 Main St,Springfield"
+    )
+    assert doc.text == "1 Main St Springfield"
+
+
+def test_get_text_normalize_and_form() -> None:
+    doc = Document("<p>caf\u0065\u0301\u00a0\u2009 bar\n\tbaz</p><p> x </p>")
+    p = doc.find("p")
+
+    assert p.get_text() == "cafe\u0301\u00a0\u2009 bar\n\tbaz"
+    assert p.get_text(normalize=True) == "cafe\u0301 bar baz"
+    assert p.get_text(normalize=True, form="NFC") == "caf\u00e9 bar baz"
+    assert p.get_text(form="NFKC") == "caf\u00e9   bar\n\tbaz"
+    assert doc.get_text("|", normalize=True) == "cafe\u0301 bar baz| x "
+    assert doc.get_text("|", strip=True, normalize=True, form="NFC") == (
+        "caf\u00e9 bar baz|x"
+    )
+    assert doc.xpath_first("//p").get_text(normalize=True) == "cafe\u0301 bar baz"
+
+    with pytest.raises(ValueError, match="normalization form"):
+        p.get_text(form="nfx")
+
+
+def test_iter_text() -> None:
+    doc = Document(
+        "<head><title>T</title><style>p {}</style></head>"
+        "<body><p>Hello <b>bold</b> world</p>\n<ul><li>one</li></ul>"
+        "<script>var x;</script><template><i>tpl</i></template></body>"
+    )
+
+    assert list(doc.iter_text()) == [
+        ("title", "T"),
+        ("p", "Hello "),
+        ("b", "bold"),
+        ("p", " world"),
+        ("body", "\n"),
+        ("li", "one"),
+    ]
+    stripped = list(doc.iter_text(strip=True))
+    assert ("body", "\n") not in stripped and ("p", "world") in stripped
+    assert " ".join(t for tag, t in stripped if tag in {"p", "li"}) == (
+        "Hello world one"
+    )
+    tags = [tag for tag, _ in doc.iter_text(include_scripts=True)]
+    assert tags == ["title", "style", "p", "b", "p", "body", "li", "script", "i"]
+
+    texts = doc.iter_text()
+    assert iter(texts) is texts
+    assert next(texts) == ("title", "T")
+    assert list(Document("").iter_text()) == []
+
+
+def test_text_skips_script_and_style() -> None:
+    html = """
+    <html>
+      <head><style>body { color: red; }</style><title>Title</title></head>
+      <body>
+        <p>Visible</p>
+        <script>var hidden = 1;</script>
+        <noscript>Enable JS</noscript>
+        <template><p>Inert</p></template>
+      </body>
+    </html>
+    """
+    doc = Document(html)
+
+    assert doc.text == "Title Visible"
+    assert doc.get_text("|", strip=True) == "Title|Visible"
+    assert doc.find("body").text == "Visible"
+    assert doc.xpath_first("//body").text == "Visible"
+    assert doc.find("script").text == "var hidden = 1;"
+
+    everything = doc.get_text("|", strip=True, include_scripts=True)
+    assert "color: red" in everything
+    assert "var hidden = 1;" in everything
+    assert "Inert" in everything
+
+
+def test_selected_element_text_skips_scripts_everywhere() -> None:
+    doc = Document(
+        '<div id="c">Hi<script>var x = 1;</script><style>p {}</style> there</div>'
+    )
+    div = doc.find("#c")
+
+    assert div.text == doc.text == "Hi there"
+    assert doc.select("div")[0].text == "Hi there"
+    assert div.to_dict()["text"] == "Hi there"
+    assert json.loads(div.to_json())["text"] == "Hi there"
+    assert pickle.loads(pickle.dumps(div)).text == "Hi there"
+    assert doc.select_containing("div", "var") == []
+    assert doc.select_text_regex("div", "var") == []
+    assert "var x = 1;" in div.get_text(include_scripts=True)
+    assert "var x = 1;" in doc.xpath_first("//div").get_text(include_scripts=True)
+
+
+def test_select_include_scripts_keeps_script_text() -> None:
+    doc = Document(
+        '<div id="c">Hi<script>var x = 1;</script><style>p {}</style> there</div>'
+    )
+    everything = "Hi var x = 1; p {} there"
+
+    [div] = doc.select("div", include_scripts=True)
+    assert div.text == everything
+    assert div.to_dict()["text"] == everything
+    assert json.loads(div.to_json())["text"] == everything
+    assert pickle.loads(pickle.dumps(div)).text == everything
+    assert doc.select("div", tags=["div"], include_scripts=True)[0].text == everything
+    assert doc.find("#c", include_scripts=True).text == everything
+    # The option only applies to the returned elements.
+    assert doc.find("#c").text == "Hi there"
+    assert div.parent().text == "Hi there"
+
+
+def test_select_family_include_scripts() -> None:
+    """Test include_scripts on the select aliases and nested selection."""
+    doc = Document('<section><div id="c">Hi <script>x = 1</script></div></section>')
+    section = doc.find("section")
+
+    text = "Hi x = 1"
+    assert doc.select_first("div", include_scripts=True).text == text
+    assert doc.select_one("div", include_scripts=True).text == text
+    assert doc.find_all("div", include_scripts=True)[0].text == text
+    assert doc.css("div", include_scripts=True)[0].text == text
+    assert section.select("div", include_scripts=True)[0].text == text
+    assert section.select_first("div", include_scripts=True).text == text
+    assert section.find("div", include_scripts=True).text == text
+    assert section.select_one("div", include_scripts=True).text == text
+    assert section.find_all("div", include_scripts=True)[0].text == text
+    assert section.css("div", include_scripts=True)[0].text == text
+
+    assert doc.select_one("div").text == "Hi"
+    assert section.find("div").text == "Hi"
+    assert section.find_all("div")[0].text == "Hi"
+
+
+def test_document_from_bytes_detects_encoding() -> None:
+    html = '<html><head><meta charset="shift_jis"></head><p>日本語</p></html>'
+    doc = Document.from_bytes(html.encode("shift_jis"))
+    assert doc.find("p").text == "日本語"
+
+    http_equiv = (
+        '<meta http-equiv="Content-Type" content="text/html; charset=windows-1252">'
+        "<p>café</p>"
+    )
+    assert Document.from_bytes(http_equiv.encode("cp1252")).find("p").text == "café"
+
+    bom = b"\xef\xbb\xbf<p>caf\xc3\xa9</p>"
+    assert Document.from_bytes(bom).find("p").text == "café"
+    assert Document.from_bytes("<p>café</p>".encode()).find("p").text == "café"
+    undeclared = "<p>café “quoted”</p>".encode("cp1252")
+    assert Document.from_bytes(undeclared).find("p").text == "café “quoted”"
+
+    latin = "<p>café</p>".encode("latin-1")
+    assert Document.from_bytes(latin, "iso-8859-1").find("p").text == "café"
+
+    with pytest.raises(ValueError, match="Unknown encoding"):
+        Document.from_bytes(b"<p>x</p>", "not-a-charset")
+
+
+def test_document_from_file(tmp_path: pathlib.Path) -> None:
+    page = tmp_path / "page.html"
+    page.write_bytes(b"\xef\xbb\xbf<title>Saved</title><p>caf\xc3\xa9</p>")
+
+    doc = Document.from_file(str(page))
+    assert doc.find("p").text == "café"
+    assert Document.from_file(page).find("title").text == "Saved"
+
+    legacy = tmp_path / "legacy.html"
+    legacy.write_bytes("<p>café</p>".encode("cp1252"))
+    assert Document.from_file(legacy, "windows-1252").find("p").text == "café"
+
+    missing = tmp_path / "missing.html"
+    with pytest.raises(OSError, match="missing.html"):
+        Document.from_file(missing)
+
+
+def test_document_title() -> None:
+    doc = Document(
+        "<html><head><title>\n  My   Page\n</title></head>"
+        "<body><title>Second</title></body></html>"
+    )
+    assert doc.title == "My Page"
+    assert Document("<p>No title</p>").title is None
+
+
+def test_document_links() -> None:
+    html = """
+    <a href="/a">  First
+      link </a>
+    <a name="anchor">No href</a>
+    <a href="https://example.com/b"><b>Second</b></a>
+    <a href="">Empty</a>
+    """
+    doc = Document(html)
+
+    assert doc.links() == [
+        ("First link", "/a"),
+        ("Second", "https://example.com/b"),
+        ("Empty", ""),
+    ]
+    assert Document("<p>none</p>").links() == []
+
+
+def test_abs_attr_resolves_against_base_url() -> None:
+    html = """
+    <a href="page">Page</a>
+    <a href="../up">Up</a>
+    <a href="https://other.example/x">Other</a>
+    <img src="/img.png">
+    <a>No href</a>
+    """
+    doc = Document(html, base_url="https://example.com/docs/guide/")
+    links = doc.select("a")
+
+    assert doc.base_url == "https://example.com/docs/guide/"
+    assert links[0].abs_attr("href") == "https://example.com/docs/guide/page"
+    assert links[1].abs_attr("href") == "https://example.com/docs/up"
+    assert links[2].abs_attr("href") == "https://other.example/x"
+    assert links[3].abs_attr("href") is None
+    assert doc.find("img").abs_attr("src") == "https://example.com/img.png"
+    assert doc.xpath_first("//img").abs_attr("src") == "https://example.com/img.png"
+    assert doc.links(absolute=True)[0] == (
+        "Page",
+        "https://example.com/docs/guide/page",
+    )
+
+    without_base = Document(html)
+    assert without_base.base_url is None
+    assert without_base.find("a").abs_attr("href") is None
+    assert without_base.select("a")[2].abs_attr("href") == "https://other.example/x"
+    assert without_base.links(absolute=True) == [("Other", "https://other.example/x")]
+
+    with pytest.raises(ValueError, match="Invalid base_url"):
+        Document(html, base_url="not a url")
+
+
+def test_abs_attr_honors_base_element() -> None:
+    html = '<head><base href="/static/"></head><body><img src="a.png"></body>'
+    doc = Document(html, base_url="https://example.com/page")
+    assert doc.base_url == "https://example.com/static/"
+    assert doc.find("img").abs_attr("src") == "https://example.com/static/a.png"
+
+    absolute = Document('<base href="https://cdn.example/"><img src="b.png">')
+    assert absolute.find("img").abs_attr("src") == "https://cdn.example/b.png"
+
+
+def test_jsonld_blocks() -> None:
+    html = """
+    <script type="application/ld+json">
+      {"@type": "Product", "name": "Widget", "price": 9.5, "stock": 3,
+       "tags": ["a", "b"], "discontinued": false, "brand": null}
+    </script>
+    <script type="application/ld+json">{ not json }</script>
+    <script type="APPLICATION/LD+JSON">[{"@type": "Thing"}]</script>
+    <script>{"@type": "Ignored"}</script>
+    """
+    doc = Document(html)
+
+    errors: list[str] = []
+    blocks = doc.jsonld(errors=errors)
+    assert blocks == [
+        {
+            "@type": "Product",
+            "name": "Widget",
+            "price": 9.5,
+            "stock": 3,
+            "tags": ["a", "b"],
+            "discontinued": False,
+            "brand": None,
+        },
+        [{"@type": "Thing"}],
+    ]
+    assert list(blocks[0]) == [
+        "@type", "name", "price", "stock", "tags", "discontinued", "brand"
+    ]
+    assert len(errors) == 1
+    assert errors[0].startswith("JSON-LD block 1:")
+    assert doc.jsonld() == blocks
+
+
+def test_meta_and_charset() -> None:
+    html = """
+    <head>
+      <meta charset="UTF-8">
+      <meta name="description" content="First">
+      <meta property="og:title" content="OG Title">
+      <meta name="description" content="Second">
+      <meta name="empty">
+    </head>
+    """
+    doc = Document(html)
+
+    assert doc.meta() == {"description": "Second", "og:title": "OG Title"}
+    assert doc.charset() == "utf-8"
+
+    http_equiv = Document(
+        '<meta http-equiv="Content-Type" content="text/html; charset=Shift_JIS">'
+    )
+    assert http_equiv.charset() == "shift_jis"
+    assert Document("<p>plain</p>").charset() is None
+    assert Document("<p>plain</p>").meta() == {}
+
+
+def test_forms_extraction() -> None:
+    html = """
+    <form action="/login" method="POST">
+      <input name="user" value="ada">
+      <input type="password" name="pass">
+      <input type="checkbox" name="remember" checked>
+      <input type="radio" name="plan" value="free">
+      <select name="lang">
+        <option value="en">English</option>
+        <option selected>Deutsch</option>
+      </select>
+      <select name="tags" multiple><option>a</option><option>b</option></select>
+      <textarea name="bio">  Hello
+ world</textarea>
+      <button name="go">Go</button>
+    </form>
+    <form></form>
+    """
+    forms = Document(html).forms()
+
+    assert len(forms) == 2
+    login, empty = forms
+    assert login["action"] == "/login"
+    assert login["method"] == "post"
+    assert empty == {"action": None, "method": "get", "fields": []}
+
+    fields = {field["name"]: field for field in login["fields"]}
+    assert list(fields) == [
+        "user", "pass", "remember", "plan", "lang", "tags", "bio", "go"
+    ]
+    assert fields["user"]["type"] == "text"
+    assert fields["user"]["value"] == "ada"
+    assert fields["pass"]["value"] == ""
+    assert fields["remember"]["checked"] is True
+    assert fields["remember"]["value"] == "on"
+    assert fields["plan"]["checked"] is False
+    assert fields["lang"]["value"] == "Deutsch"
+    assert fields["lang"]["options"] == ["en", "Deutsch"]
+    assert fields["tags"]["type"] == "select-multiple"
+    assert fields["tags"]["value"] is None
+    assert fields["bio"]["value"] == "  Hello\n world"
+    assert fields["go"]["type"] == "submit"
+
+
+def test_tables_to_dicts() -> None:
+    html = """
+    <table id="people">
+      <tr><th>Name</th><th>Age</th><th>City</th></tr>
+      <tr><td>Ada</td><td>36</td><td>London</td></tr>
+      <tr><td>Linus</td><td>28</td></tr>
+      <tr><td colspan="2">Unknown</td><td>Paris</td></tr>
+    </table>
+    <table>
+      <thead><tr><th>Key</th><th></th><th>Key</th></tr></thead>
+      <tbody>
+        <tr><td>a</td><td>b</td><td>c</td><td>extra</td></tr>
+        <tr><td><table><tr><th>Inner</th></tr><tr><td>x</td></tr></table></td></tr>
+      </tbody>
+    </table>
+    """
+    doc = Document(html)
+    tables = doc.tables()
+
+    assert len(tables) == 3
+    assert tables[0] == [
+        {"Name": "Ada", "Age": "36", "City": "London"},
+        {"Name": "Linus", "Age": "28", "City": ""},
+        {"Name": "Unknown", "Age": "Unknown", "City": "Paris"},
+    ]
+    assert list(tables[0][0]) == ["Name", "Age", "City"]
+    assert tables[1] == [
+        {"Key": "a", "1": "b", "Key.1": "c", "3": "extra"},
+        {"Key": "Inner x", "1": "", "Key.1": ""},
+    ]
+    assert tables[2] == [{"Inner": "x"}]
+
+    assert doc.by_id("people").as_table() == tables[0]
+    assert doc.xpath_first("//table[@id='people']").as_table() == tables[0]
+    with pytest.raises(ValueError, match="<table>"):
+        doc.find("td").as_table()
+
+
+def test_table_matrix_expands_spans() -> None:
+    html = """
+    <table>
+      <thead><tr><th rowspan="2">Region</th><th colspan="2">Sales</th></tr></thead>
+      <tbody>
+        <tr><td>Q1</td><td>Q2</td></tr>
+        <tr><td rowspan="0">North</td><td>1</td><td>2</td></tr>
+        <tr><td>3</td></tr>
+        <tr><td>4</td><td rowspan="5">5</td></tr>
+      </tbody>
+    </table>
+    """
+    doc = Document(html)
+    table = doc.find("table")
+
+    assert table.table_matrix() == [
+        ["Region", "Sales", "Sales"],
+        ["Region", "Q1", "Q2"],
+        ["North", "1", "2"],
+        ["North", "3", ""],
+        ["North", "4", "5"],
+    ]
+    assert doc.xpath_first("//table").table_matrix() == table.table_matrix()
+    assert Document("<table></table>").find("table").table_matrix() == []
+    with pytest.raises(ValueError, match="table_matrix"):
+        doc.find("td").table_matrix()
+
+
+def test_parse_xml_keeps_case() -> None:
+    feed = Document.parse_xml(
+        '<?xml version="1.0"?><rss xmlns:dc="http://purl.org/dc/elements/1.1/">'
+        "<channel><title>Feed</title><item><title>A &amp; B</title>"
+        "<dc:creator>Ann</dc:creator><link>http://x/1</link>"
+        '<Guid isPermaLink="false">1</Guid></item></channel></rss>'
+    )
+
+    assert [el.tag for el in feed.walk()][:3] == ["rss", "channel", "title"]
+    assert feed.find("head") is None
+    assert feed.find("Guid").attrs == {"isPermaLink": "false"}
+    assert feed.find("guid") is None
+    assert feed.find("item > link").text == "http://x/1"
+    assert feed.find("creator").text == "Ann"
+    assert [el.text for el in feed.select("item > title")] == ["A & B"]
+    assert [el.tag for el in feed.xpath("//Guid")] == ["Guid"]
+    assert feed.errors() == []
+
+    restored = pickle.loads(pickle.dumps(feed))
+    assert restored.find("Guid") is not None
+
+    assert Document.parse_xml("<a><b></a>").errors()
+
+
+def test_parse_many() -> None:
+    pages = [f"<p>{i}</p>" for i in range(50)]
+    docs = parse_many(pages)
+
+    assert [doc.find("p").text for doc in docs] == [str(i) for i in range(50)]
+    assert parse_many([]) == []
+    assert parse_many(("<li>a</li>",), fragment=True)[0].find("li").parent() is None
+
+    with pytest.raises(ValueError, match="too large"):
+        parse_many(["<p>ok</p>", "<p>" + "x" * 100 + "</p>"], max_size_bytes=20)
+    with pytest.raises(TypeError):
+        parse_many("<p>not a list</p>")
+
+
+def test_parse_and_select_from_threads() -> None:
+    pages = ["<div>" + "<p>x</p>" * (i + 1) + "</div>" for i in range(16)]
+
+    def count_paragraphs(html: str) -> int:
+        return len(Document(html).select("p"))
+
+    with ThreadPoolExecutor(max_workers=4) as pool:
+        counts = list(pool.map(count_paragraphs, pages))
+
+    assert counts == list(range(1, 17))
+
+
+def test_parse_fragment() -> None:
+    snippet = "<li>a</li><li>b</li>"
+
+    for doc in (Document.parse_fragment(snippet), parse(snippet, fragment=True)):
+        items = doc.select("li")
+        assert [item.text for item in items] == ["a", "b"]
+        assert items[0].parent() is None
+        assert items[0].next_sibling().text == "b"
+        assert items[0].closest("*").tag == "li"
+        assert doc.select("html, head, body") == []
+        assert doc.count("*") == 2
+        assert doc.text == "a b"
+
+    full = Document(snippet)
+    assert full.count("html, head, body") == 3
+    assert full.find("li").parent().tag == "body"
+
+
+def test_content_and_structure_hash() -> None:
+    base = Document("<div><p>Hello world</p><p>Bye</p></div>")
+    spaced = Document('<div class="x">\n  <p>Hello   world</p>\n  <p>Bye</p></div>')
+    retexted = Document("<div><p>Other</p><p>Text</p></div>")
+    reshaped = Document("<div><p>Hello world</p><span>Bye</span></div>")
+
+    assert len(base.content_hash()) == 64
+    assert base.content_hash() == spaced.content_hash()
+    assert base.content_hash() != retexted.content_hash()
+    assert base.content_hash() == reshaped.content_hash()
+
+    assert base.structure_hash() == spaced.structure_hash()
+    assert base.structure_hash() == retexted.structure_hash()
+    assert base.structure_hash() != reshaped.structure_hash()
+
+
+def test_text_excluding() -> None:
+    doc = Document(
+        "<nav>Home About</nav><div id='cookie'>Accept?</div>"
+        "<main><p>Real <span class='ad'>BUY</span>content</p><script>x()</script></main>"
+    )
+
+    assert doc.text_excluding(["nav", ".ad", "#cookie"]) == "Real content"
+    assert doc.text_excluding([]) == doc.text
+
+    with pytest.raises(SelectorError):
+        doc.text_excluding(["nav["])
+
+
+def test_to_text_block_formatting() -> None:
+    doc = Document(
+        "<html><head><title>T</title><style>p{}</style></head><body>"
+        "<h1>Title</h1><p>First   <b>bold</b>\n paragraph.</p>"
+        "<p>Line one<br>Line two</p>"
+        "<ul><li>apple</li><li>pear<ol start='3'><li>a</li><li>b</li></ol></li></ul>"
+        "<pre>  keep\n    this</pre><div>end</div>"
+        "</body></html>"
+    )
+
+    assert doc.to_text() == (
+        "Title\n"
+        "\n"
+        "First bold paragraph.\n"
+        "\n"
+        "Line one\n"
+        "Line two\n"
+        "\n"
+        "- apple\n"
+        "- pear\n"
+        "  3. a\n"
+        "  4. b\n"
+        "\n"
+        "  keep\n"
+        "    this\n"
+        "\n"
+        "end"
+    )
+    assert doc.find("ol").to_text() == "3. a\n4. b"
+    assert doc.xpath_first("//ul").to_text() == doc.find("ul").to_text()
+
+
+def test_to_markdown() -> None:
+    doc = Document(
+        "<article><h2>Intro <em>now</em></h2>"
+        "<p>Read <a href='/docs'>the <b>docs</b></a> or use <code>a_b()</code>.</p>"
+        "<ul><li>one</li><li>two <strong> strong </strong>!</li></ul>"
+        "<blockquote><p>quoted</p></blockquote>"
+        "<pre><code>x = 1\ny = 2</code></pre>"
+        "<p>2 * 3 <span>plain</span> <img src='/i.png' alt='pic'></p></article>"
+    )
+
+    assert doc.to_markdown() == (
+        "## Intro *now*\n"
+        "\n"
+        "Read [the **docs**](/docs) or use `a_b()`.\n"
+        "\n"
+        "- one\n"
+        "- two **strong** !\n"
+        "\n"
+        "> quoted\n"
+        "\n"
+        "```\n"
+        "x = 1\n"
+        "y = 2\n"
+        "```\n"
+        "\n"
+        "2 \\* 3 plain ![pic](/i.png)"
+    )
+    assert doc.find("li:nth-child(2)").to_markdown() == "- two **strong** !"
+
+
+def test_sanitize() -> None:
+    doc = Document(
+        "<head><title>t</title></head><body>"
+        '<p class="x" onclick="evil()">Hi <b>there</b><!-- note --></p>'
+        "<script>alert(1)</script><style>p{}</style><iframe src='/x'></iframe>"
+        '<a href=" javascript:alert(1)" title="t">bad</a> <a href="/ok">ok</a>'
+        "<div><span>kept</span><br></div></body>"
+    )
+
+    assert doc.sanitize() == (
+        '<p class="x">Hi <b>there</b></p>'
+        '<a title="t">bad</a> <a href="/ok">ok</a>'
+        "<div><span>kept</span><br></div>"
+    )
+    assert doc.sanitize(allowed_tags=["p", "a"], allowed_attrs=["href"]) == (
+        '<p>Hi there</p><a>bad</a> <a href="/ok">ok</a>kept'
+    )
+    assert "<iframe" in doc.sanitize(allowed_tags=["iframe"])
+
+    fragment = Document.parse_fragment("<li onmouseover='x()'>a</li><script>x</script>")
+    assert fragment.sanitize() == "<li>a</li>"
+
+
+def test_sanitize_drops_redirecting_and_active_elements() -> None:
+    doc = Document(
+        "<body><p>Hi</p>"
+        '<meta http-equiv="refresh" content="0;url=https://evil.example/">'
+        '<base href="https://evil.example/">'
+        '<object data="/x.swf"></object><embed src="/x.swf">'
+        '<form action="https://evil.example/"><input name="q"></form>'
+        "</body>"
+    )
+
+    assert doc.sanitize() == "<p>Hi</p>"
+    assert "<form" in doc.sanitize(allowed_tags=["form", "input"])
+
+
+def test_sanitize_drops_data_urls_and_style() -> None:
+    doc = Document(
+        '<a href="data:text/html,<script>alert(1)</script>">x</a>'
+        '<img src=" DATA:image/svg+xml,<svg/>" alt="data: kept">'
+        '<p style="background:url(javascript:x)" title="t">y</p>'
+    )
+
+    assert doc.sanitize() == '<a>x</a><img alt="data: kept"><p title="t">y</p>'
+    assert doc.sanitize(allowed_attrs=["style"]) == (
+        '<a>x</a><img><p style="background:url(javascript:x)">y</p>'
+    )
+
+
+def test_prettify() -> None:
+    doc = Document(
+        '<!DOCTYPE html><title>T</title><div id="a"><p>Hi <b>x &amp; y</b></p>'
+        "<br><!-- c --><pre> keep\n me</pre></div>"
+    )
+
+    assert doc.prettify() == (
+        "<!DOCTYPE html>\n"
+        "<html>\n"
+        "  <head>\n"
+        "    <title>\n"
+        "      T\n"
+        "    </title>\n"
+        "  </head>\n"
+        "  <body>\n"
+        '    <div id="a">\n'
+        "      <p>\n"
+        "        Hi\n"
+        "        <b>\n"
+        "          x &amp; y\n"
+        "        </b>\n"
+        "      </p>\n"
+        "      <br>\n"
+        "      <!-- c -->\n"
+        "      <pre> keep\n me</pre>\n"
+        "    </div>\n"
+        "  </body>\n"
+        "</html>\n"
+    )
+    fragment = Document.parse_fragment("<li>a</li><li>b</li>")
+    assert fragment.prettify(indent=4) == "<li>\n    a\n</li>\n<li>\n    b\n</li>\n"
+
+
+def test_minify() -> None:
+    html = """<!DOCTYPE html>
+<html>
+  <head>
+    <title>T</title>
+  </head>
+  <body>
+    <div id="a">
+      <p>Hi   <b>x &amp; y</b> <i>z</i>\u00a0!</p>
+      <!-- c -->
+      <pre> keep
+ me</pre>
+      <textarea>  a  b  </textarea>
+      <script> if (a < b) {} </script>
+    </div>
+  </body>
+</html>
+"""
+    doc = Document(html)
+    minified = doc.minify()
+
+    assert minified == (
+        "<!DOCTYPE html><html><head><title>T</title></head><body>"
+        '<div id="a"><p>Hi <b>x &amp; y</b> <i>z</i>\u00a0!</p><!-- c -->'
+        "<pre> keep\n me</pre> <textarea>  a  b  </textarea> "
+        "<script> if (a < b) {} </script></div></body></html>"
+    )
+    assert len(minified) < len(html)
+    assert Document(minified).minify() == minified
+    fragment = Document.parse_fragment("<span>a</span> <span>b</span>")
+    assert fragment.minify() == "<span>a</span> <span>b</span>"
+
+
+def test_remove_and_to_html() -> None:
+    html = (
+        "<!DOCTYPE html><p>Keep<script>track()</script></p>"
+        '<div class="ad">Buy <b>now</b></div><p>Also</p>'
+    )
+    doc = Document(html)
+    assert doc.to_html() == (
+        "<!DOCTYPE html><html><head></head><body><p>Keep<script>track()</script>"
+        '</p><div class="ad">Buy <b>now</b></div><p>Also</p></body></html>'
+    )
+    assert len(doc.xpath("//div")) == 1
+
+    ad = doc.find(".ad")
+    for node in doc.select("script, .ad"):
+        node.remove()
+
+    assert doc.to_html() == (
+        "<!DOCTYPE html><html><head></head><body><p>Keep</p><p>Also</p></body></html>"
+    )
+    assert doc.html == html
+    assert doc.text == "Keep Also"
+    assert doc.select(".ad, b") == []
+    assert doc.xpath("//div") == []
+    assert ad.text == "Buy now" and ad.parent() is None
+    ad.remove()
+    assert pickle.loads(pickle.dumps(doc)).to_html() == doc.to_html()
+    assert copy.copy(doc).find(".ad") is None
+
+    with pytest.raises(ValueError, match="root"):
+        doc.root().remove()
+    with pytest.raises(ValueError, match="snapshot"):
+        doc.xpath_first("//p").remove()
+
+    fragment = Document.parse_fragment("<li>a</li><li>b</li>")
+    fragment.find("li").remove()
+    assert fragment.to_html() == "<li>b</li>"
+
+    doc = Document("<ul><li>1<li>2<li>3</ul><p>x</p>")
+    for li in doc.iter_select("li, p"):
+        li.remove()
+    assert doc.to_html() == "<html><head></head><body><ul></ul></body></html>"
+
+
+def test_set_attr_and_remove_attr() -> None:
+    doc = Document(
+        '<a id="old" class="nav" href="/p?utm=1" onclick="go()">x</a>',
+        base_url="https://example.com/",
+    )
+    link = doc.find("a")
+    assert doc.find(".nav") is not None
+
+    link.set_attr("href", link.abs_attr("href"))
+    link.set_attr("class", "main")
+    link.set_attr("id", "new")
+    link.set_attr("data-note", '"<&')
+    link.remove_attr("onclick")
+    link.remove_attr("missing")
+
+    assert link.attrs == {
+        "id": "new",
+        "class": "main",
+        "href": "https://example.com/p?utm=1",
+        "data-note": '"<&',
+    }
+    assert link.id == "new" and link.classes() == ["main"]
+    assert doc.find(".nav") is None and doc.find("#old") is None
+    assert doc.find("a.main#new") == link
+    assert doc.to_html() == (
+        '<html><head></head><body><a id="new" class="main" '
+        'href="https://example.com/p?utm=1" data-note="&quot;<&amp;">x</a>'
+        "</body></html>"
+    )
+    assert doc.xpath_first("//a[@id='new']") is not None
+
+    for name in ("", "a b", "x=", "<y"):
+        with pytest.raises(ValueError, match="Invalid attribute name"):
+            link.set_attr(name, "v")
+    with pytest.raises(ValueError, match="snapshot"):
+        doc.xpath_first("//a").set_attr("id", "z")
+
+
+def test_edited_element_stays_in_set() -> None:
+    doc = Document('<div class="a"><p>x</p><p>y</p></div>')
+    div = doc.find("div")
+    seen = {div}
+    index = {div: "first"}
+
+    div.set_attr("class", "z")
+    doc.select("p")[1].remove()
+    div.remove_attr("class")
+
+    assert div in seen
+    assert index[div] == "first"
+    assert len({div, *seen}) == 1
+    assert doc.find("div") == div
+
+
+def test_parse_errors_and_quirks_mode() -> None:
+    clean = Document("<!DOCTYPE html><html><head></head><body><p>ok</p></body></html>")
+    assert clean.errors() == []
+    assert clean.quirks_mode == "no-quirks"
+
+    messy = Document("<p>unclosed <b>bold</p></i>")
+    assert messy.errors()
+    assert all(isinstance(err, str) for err in messy.errors())
+    assert messy.quirks_mode == "quirks"
+
+    legacy = Document(
+        '<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Transitional//EN" '
+        '"http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd"><p>x</p>'
+    )
+    assert legacy.quirks_mode == "limited-quirks"
+
+
+def test_document_walk() -> None:
+    doc = Document("<div><h2>A</h2><p>one</p><h2>B</h2><p>two</p></div>")
+    assert [el.tag for el in doc.walk()] == [
+        "html",
+        "head",
+        "body",
+        "div",
+        "h2",
+        "p",
+        "h2",
+        "p",
+    ]
+
+    sections: dict[str, list[str]] = {}
+    current = None
+    for el in doc.walk():
+        if el.tag == "h2":
+            current = sections.setdefault(el.text, [])
+        elif el.tag == "p" and current is not None:
+            current.append(el.text)
+    assert sections == {"A": ["one"], "B": ["two"]}
+
+    fragment = Document.parse_fragment("<li>x</li><li>y</li>")
+    assert [el.tag for el in fragment.walk()] == ["li", "li"]
+
+
+def test_element_matches() -> None:
+    doc = Document('<ul class="nav"><li><a href="/x" class="btn">x</a></li></ul>')
+    link = doc.find("a")
+
+    assert link.matches("a")
+    assert link.matches("a.btn[href^='/']")
+    assert link.matches("ul.nav > li > a")
+    assert not link.matches("div a")
+    assert not link.matches("span")
+
+    snapshot = doc.xpath_first("//a")
+    assert snapshot.matches("a.btn")
+    assert not snapshot.matches("p")
+
+    with pytest.raises(SelectorError):
+        link.matches("a[")
+
+
+def test_find_parent_and_parents() -> None:
+    doc = Document(
+        "<div class='box outer'><div class='box'><p><span>x</span></p></div></div>"
+    )
+    span = doc.find("span")
+
+    assert span.find_parent("p").tag == "p"
+    assert span.find_parent(".box").attrs == {"class": "box"}
+    assert doc.find("p").find_parent("p") is None
+    assert doc.find("p").closest("p").tag == "p"
+    assert [el.attrs.get("class") for el in span.find_parents("div")] == [
+        "box",
+        "box outer",
+    ]
+    assert [el.tag for el in span.find_parents("*")] == [
+        "p",
+        "div",
+        "div",
+        "body",
+        "html",
+    ]
+    assert doc.xpath_first("//span").find_parents("*") == []
+
+
+def test_find_next_and_previous() -> None:
+    doc = Document(
+        "<div><span class='label'>Price</span></div>"
+        "<section><p><b class='value'>10</b></p></section>"
+        "<h2>Other</h2><b class='value'>20</b>"
+    )
+    label = doc.find(".label")
+
+    assert label.find_next(".value").text == "10"
+    assert doc.find("section").find_next("b").text == "10"
+    assert doc.find("h2").find_next(".value").text == "20"
+    assert doc.find("h2").find_next("span") is None
+
+    value = doc.select(".value")[1]
+    assert value.find_previous("h2").text == "Other"
+    assert value.find_previous(".label").text == "Price"
+    assert doc.find("b").find_previous("section").tag == "section"
+    assert doc.find("html").find_previous("*") is None
+
+    with pytest.raises(SelectorError):
+        label.find_next("[")
+
+
+def test_element_depth_and_sibling_index() -> None:
+    doc = Document("<ul><li>a</li> <!-- c --> <li>b</li><li>c</li></ul>")
+
+    assert doc.find("html").depth() == 0
+    assert doc.find("body").depth() == 1
+    assert [li.depth() for li in doc.select("li")] == [3, 3, 3]
+    assert [li.sibling_index() for li in doc.select("li")] == [0, 1, 2]
+    assert doc.find("body").sibling_index() == 1
+
+    fragment = Document.parse_fragment("<p>x</p><p><b>y</b></p>")
+    assert [p.depth() for p in fragment.select("p")] == [0, 0]
+    assert fragment.find("b").depth() == 1
+
+    assert doc.xpath_first("//li").depth() is None
+    assert doc.xpath_first("//li").sibling_index() is None
+
+
+def test_element_css_path() -> None:
+    doc = Document(
+        '<div class="content"><ul><li>a</li><li>b</li><li class="x y">c</li></ul>'
+        '</div><section id="main"><p>one</p><p>two</p></section>'
+        '<span id="dup"></span><span id="dup"><b>z</b></span>'
+    )
+
+    third = doc.select("li")[2]
+    assert third.css_path() == "html > body > div.content > ul > li.x.y:nth-child(3)"
+    assert doc.find("ul").css_path() == "html > body > div.content > ul"
+    assert doc.select("p")[1].css_path() == "section#main > p:nth-child(2)"
+    assert doc.find("b").css_path() == "html > body > span:nth-child(4) > b"
+
+    for el in doc.walk():
+        matches = doc.select(el.css_path())
+        assert len(matches) == 1
+        assert matches[0].outer_html == el.outer_html
+
+    assert doc.xpath_first("//li").css_path() is None
+
+
+def test_pluck() -> None:
+    doc = Document(
+        "<div class='product'><a href='/a'>A</a><span class='price'> 1 </span></div>"
+        "<div class='product'><a>B</a></div>"
+        "<div class='product'><a href='/c'>C</a><span class='price'>3</span></div>"
+        "<span class='price'>outside</span>"
+    )
+
+    assert doc.pluck(".product", ".price") == ["1", None, "3"]
+    assert doc.pluck(".product", "a", attr="href") == ["/a", None, "/c"]
+    assert doc.pluck(".missing", "a") == []
+
+
+def test_select_in() -> None:
+    doc = Document(
+        "<div class='post'><span class='tag'>a</span><span class='tag'>b</span></div>"
+        "<div class='post'><p>no tags</p></div>"
+        "<div class='post'><span class='tag'>c</span></div>"
+        "<span class='tag'>outside</span>"
+    )
+
+    groups = doc.select_in(".post", ".tag")
+    assert [[tag.text for tag in tags] for tags in groups] == [["a", "b"], [], ["c"]]
+    assert groups[0][0].parent() == doc.select(".post")[0]
+    assert doc.select_in(".missing", ".tag") == []
+    with pytest.raises(SelectorError):
+        doc.select_in(".post", "span[")
+
+
+def test_records() -> None:
+    doc = Document(
+        "<ul><li class='item'><h2>A</h2><a href='/a'>more</a><i>new</i></li>"
+        "<li class='item'><h2>B</h2></li></ul>"
+    )
+
+    records = doc.records(
+        ".item",
+        {"name": ("h2", None), "url": ("a", "href"), "badge": ("i", None)},
+    )
+    assert records == [
+        {"name": "A", "url": "/a", "badge": "new"},
+        {"name": "B", "url": None, "badge": None},
+    ]
+    assert list(records[0]) == ["name", "url", "badge"]
+
+    with pytest.raises(SelectorError):
+        doc.records(".item", {"bad": ("a[", None)})
+
+
+def test_select_csv() -> None:
+    doc = Document('<a href="/a" title=\'say "hi"\'>A, first</a><a>B</a>')
+
+    text = doc.select_csv("a", ["tag", "text", "attr:href", "attr:title"])
+    assert text == (
+        "tag,text,attr:href,attr:title\n"
+        'a,"A, first",/a,"say ""hi"""\n'
+        "a,B,,\n"
+    )
+    assert list(csv.reader(io.StringIO(text))) == [
+        ["tag", "text", "attr:href", "attr:title"],
+        ["a", "A, first", "/a", 'say "hi"'],
+        ["a", "B", "", ""],
+    ]
+    assert doc.select_csv("table", ["text"]) == "text\n"
+
+    with pytest.raises(ValueError, match="Invalid column"):
+        doc.select_csv("a", ["href"])
+
+
+def test_select_map() -> None:
+    doc = Document(
+        "<h1>Shop</h1><p class='price'>1</p><p class='price'>2</p><a href='/x'>x</a>"
+    )
+
+    found = doc.select_map({"title": "h1", "prices": ".price", "missing": "table"})
+    assert list(found) == ["title", "prices", "missing"]
+    assert [el.text for el in found["title"]] == ["Shop"]
+    assert [el.text for el in found["prices"]] == ["1", "2"]
+    assert found["missing"] == []
+
+    with pytest.raises(SelectorError):
+        doc.select_map({"ok": "a", "bad": "p["})
+
+
+def test_iter_select() -> None:
+    doc = Document("".join(f"<p class='row'>{i}</p>" for i in range(5)))
+
+    rows = doc.iter_select(".row")
+    assert iter(rows) is rows
+    assert next(rows).text == "0"
+    assert [el.text for el in rows] == ["1", "2", "3", "4"]
+    with pytest.raises(StopIteration):
+        next(rows)
+
+    assert [el.text for el in doc.iter_select("p")] == [
+        el.text for el in doc.select("p")
+    ]
+    assert list(doc.iter_select("table")) == []
+
+    fragment = Document.parse_fragment("<li>a</li><li>b</li>")
+    assert [el.tag for el in fragment.iter_select("*")] == ["li", "li"]
+
+    with pytest.raises(SelectorError):
+        doc.iter_select("p[")
+
+
+def test_select_limit() -> None:
+    doc = Document("".join(f"<p>{i}</p>" for i in range(20)))
+
+    assert [el.text for el in doc.select_limit("p", 3)] == ["0", "1", "2"]
+    assert len(doc.select_limit("p", 100)) == 20
+    assert doc.select_limit("p", 0) == []
+    assert doc.select_limit("span", 5) == []
+    with pytest.raises(ValueError):
+        doc.select_limit("p[", 1)
+    with pytest.raises(OverflowError):
+        doc.select_limit("p", -1)
+
+
+def test_select_containing() -> None:
+    html = """
+    <button>Cancel</button>
+    <button>  Submit
+      order</button>
+    <button>Ünïcode SUBMIT</button>
+    """
+    doc = Document(html)
+
+    assert [el.text for el in doc.select_containing("button", "Submit order")] == [
+        "Submit order"
+    ]
+    matches = doc.select_containing("button", "submit", case_insensitive=True)
+    assert [el.text for el in matches] == ["Submit order", "Ünïcode SUBMIT"]
+    assert doc.select_containing("button", "submit") == []
+    assert len(doc.select_containing("button", "ünï", True)) == 1
+    assert len(doc.select_containing("button", "")) == 3
+
+
+def test_escape_css() -> None:
+    assert escape_css("plain-id_1") == "plain-id_1"
+    assert escape_css("1st") == "\\31 st"
+    assert escape_css("-2x") == "-\\32 x"
+    assert escape_css("-") == "\\-"
+    assert escape_css("a.b:c[d]") == "a\\.b\\:c\\[d\\]"
+    assert escape_css("caf\u00e9 \x07") == "caf\u00e9\\ \\7 "
+    assert escape_css("\0") == "\ufffd"
+
+    doc = Document('<p id="1.5 a:b">x</p><p class="w-1/2">y</p>')
+    assert doc.find(f"#{escape_css('1.5 a:b')}").text == "x"
+    assert doc.find(f".{escape_css('w-1/2')}").text == "y"
+
+
+def test_set_repr_text_width() -> None:
+    doc = Document('<p id="intro" class="lead big">' + "word " * 20 + "</p>")
+    p = doc.find("p")
+
+    assert repr(p) == (
+        "<Element tag='p' id=\"intro\" class=\"lead big\" text="
+        + ("word " * 8)[:40]
+        + "...>"
+    )
+    try:
+        set_repr_text_width(4)
+        assert repr(p).endswith("text=word...>")
+        set_repr_text_width(1000)
+        assert repr(p).endswith("word word>")
+    finally:
+        set_repr_text_width(40)
+
+
+def test_element_repr_escapes_id_and_class() -> None:
+    odd = Document('<b id=\'a"b\' class="x>y">t</b>').find("b")
+
+    assert repr(odd) == "<Element tag='b' id=\"a\\\"b\" class=\"x>y\" text=t>"
+
+
+def test_select_text_regex() -> None:
+    html = """
+    <ul>
+      <li> $4.99 </li>
+      <li>$12.50 each</li>
+      <li>$7</li>
+      <li>
+        $100.00</li>
+    </ul>
+    """
+    doc = Document(html)
+
+    prices = doc.select_text_regex("li", r"^\$\d+\.\d{2}$")
+    assert [el.text for el in prices] == ["$4.99", "$100.00"]
+    assert len(doc.select_text_regex("li", r"\$\d")) == 4
+    assert doc.select_text_regex("p", ".") == []
+
+    with pytest.raises(ValueError, match="Invalid regex"):
+        doc.select_text_regex("li", "(")
+    with pytest.raises(SelectorError):
+        doc.select_text_regex("li[", ".")
+
+
+def test_count_and_exists(sample_html: str) -> None:
+    doc = Document(sample_html)
+
+    assert doc.count("div.item") == len(doc.select("div.item"))
+    assert doc.count("table") == 0
+    assert doc.exists("div.item")
+    assert not doc.exists("table")
+    with pytest.raises(ValueError):
+        doc.count("div[")
+    with pytest.raises(ValueError):
+        doc.exists("div[")
+
+
+def test_select_attr() -> None:
+    html = """
+    <a href="/a">A</a>
+    <a>No link</a>
+    <a href="/b">B</a>
+    """
+    doc = Document(html)
+
+    assert doc.select_attr("a", "href") == ["/a", None, "/b"]
+    assert doc.select_attr("a[href]", "href") == ["/a", "/b"]
+    assert doc.select_attr("p", "href") == []
+
+    with pytest.raises(ValueError):
+        doc.select_attr("a[", "href")
+
+
+def test_select_attrs() -> None:
+    doc = Document(
+        '<a href="/a" title="A" data-id="1" class="x" rel="nofollow">A</a>'
+        '<a data-id="2">B</a>'
+    )
+
+    rows = doc.select_attrs("a", ["href", "data-id", "title"])
+    assert rows == [
+        {"href": "/a", "data-id": "1", "title": "A"},
+        {"href": None, "data-id": "2", "title": None},
+    ]
+    assert list(rows[0]) == ["href", "data-id", "title"]
+    assert doc.select_attrs("a", []) == [{}, {}]
+    assert doc.select_attrs("p", ["href"]) == []
+    with pytest.raises(SelectorError):
+        doc.select_attrs("a[", ["href"])
+
+
 def test_find_and_first_helpers(sample_html: str) -> None:
     doc = Document(sample_html)
 
@@ -186,6 +2214,66 @@ def test_css_alias_and_invalid_selector(sample_html: str) -> None:
     assert [link.text for link in css_links] == ["First", "Second"]
 
 
+def test_beautifulsoup_aliases(sample_html: str) -> None:
+    doc = Document(sample_html)
+    item = doc.select_one(".item")
+
+    assert item == doc.find(".item")
+    assert doc.find_all("a[href]") == doc.select("a[href]")
+    assert doc.select_one(".missing") is None
+    assert item.select_one("a").text == "First"
+    assert [link.text for link in item.find_all("a")] == ["First"]
+    with pytest.raises(SelectorError):
+        doc.find_all("div[")
+
+
+def test_repeated_selectors_use_cache(sample_html: str) -> None:
+    doc = Document(sample_html)
+
+    for _ in range(3):
+        assert [link.text for link in doc.select("a[href]")] == ["First", "Second"]
+        assert doc.find("a[href]").text == "First"
+        assert len(doc.css(".item")) == 2
+
+    for _ in range(2):
+        with pytest.raises(ValueError, match="Invalid CSS selector"):
+            doc.select("div[")
+
+
+def test_selector_error_is_a_value_error(sample_html: str) -> None:
+    doc = Document(sample_html)
+    item = doc.find(".item")
+
+    assert issubclass(SelectorError, ValueError)
+    for call in (
+        lambda: doc.select("div["),
+        lambda: doc.find("div["),
+        lambda: doc.css("div["),
+        lambda: item.select("a["),
+        lambda: CssSelector("div["),
+        lambda: select(sample_html, "div["),
+    ):
+        with pytest.raises(SelectorError, match="Invalid CSS selector"):
+            call()
+
+    with pytest.raises(ValueError) as excinfo:
+        doc.xpath("//div[")
+    assert not isinstance(excinfo.value, SelectorError)
+
+
+def test_compiled_selector(sample_html: str) -> None:
+    doc = Document(sample_html)
+    sel = CssSelector("div.item a")
+
+    assert sel.css == "div.item a"
+    assert [link.attr("href") for link in doc.select_compiled(sel)] == ["/a", "/b"]
+    assert doc.find_compiled(sel).text == "First"
+    assert doc.find_compiled(CssSelector("p")) is None
+
+    with pytest.raises(ValueError, match="Invalid CSS selector"):
+        CssSelector("div[")
+
+
 def test_element_nested_selection(sample_html: str) -> None:
     doc = Document(sample_html)
 
@@ -206,6 +2294,100 @@ def test_element_nested_selection(sample_html: str) -> None:
     assert [link.tag for link in item.css("a")] == ["a"]
 
 
+def test_element_parent_navigation() -> None:
+    html = """
+    <ul data-list="prices">
+      <li data-sku="a1"><span class="price">$1</span></li>
+    </ul>
+    """
+    doc = Document(html)
+    price = doc.find(".price")
+
+    item = price.parent()
+    assert item is not None
+    assert item.tag == "li"
+    assert item.attr("data-sku") == "a1"
+    assert item.parent().attr("data-list") == "prices"
+
+    ancestors = []
+    node = price
+    while node is not None:
+        ancestors.append(node.tag)
+        node = node.parent()
+    assert ancestors == ["span", "li", "ul", "body", "html"]
+
+    assert doc.xpath_first("//li").parent() is None
+
+
+def test_element_closest() -> None:
+    html = """
+    <table id="outer"><tr><td>
+      <table id="inner"><tr><td class="price">9.99</td></tr></table>
+    </td></tr></table>
+    """
+    doc = Document(html)
+    cell = doc.find("td.price")
+
+    assert cell.closest("table").id == "inner"
+    assert cell.closest("table#outer").id == "outer"
+    assert cell.closest("td").attr("class") == "price"
+    assert cell.closest("section") is None
+    assert doc.xpath_first("//td[@class='price']").closest("td") is None
+    with pytest.raises(ValueError):
+        cell.closest("table[")
+
+
+def test_element_children_and_descendants() -> None:
+    html = "<ul>text<li>a</li><!-- note --><li>b <b>bold</b></li></ul>"
+    doc = Document(html)
+    ul = doc.find("ul")
+
+    assert [child.text for child in ul.children()] == ["a", "b bold"]
+    assert [el.tag for el in ul.descendants()] == ["li", "li", "b"]
+    assert doc.find("b").children() == []
+    assert doc.xpath_first("//ul").children() == []
+
+
+def test_element_siblings() -> None:
+    html = """
+    <dl>
+      <dt>Color</dt>
+      <!-- swatch -->
+      <dd>Red</dd>
+      <dt>Size</dt>
+      <dd>M</dd>
+    </dl>
+    """
+    doc = Document(html)
+    color, size = doc.select("dt")
+
+    assert color.next_sibling().text == "Red"
+    assert size.next_sibling().text == "M"
+    assert size.previous_sibling().text == "Red"
+    assert color.previous_sibling() is None
+    assert doc.select("dd")[-1].next_sibling() is None
+
+
+def test_element_select_is_scoped_to_subtree() -> None:
+    html = """
+    <ul class="products">
+      <li class="product"><span class="price">1</span></li>
+      <li class="product"><span class="price">2</span><span class="price">3</span></li>
+    </ul>
+    """
+    doc = Document(html)
+    first_product, second_product = doc.select(".product")
+
+    assert [p.text for p in first_product.select(".price")] == ["1"]
+    assert [p.text for p in second_product.select(".price")] == ["2", "3"]
+    assert second_product.find(".price").text == "2"
+    assert [p.text for p in second_product.select(":scope > span + span")] == ["3"]
+    assert first_product.select(".product") == []
+
+    nested = doc.xpath_first("//li[2]")
+    assert [p.text for p in nested.select(".price")] == ["2", "3"]
+
+
 def test_xpath_selection(sample_html: str) -> None:
     doc = Document(sample_html)
 
@@ -223,6 +2405,20 @@ def test_xpath_selection(sample_html: str) -> None:
     assert nested[0].attr("href") == "/a"
 
 
+def test_xpath_is_lazy_and_reports_invalid_expressions() -> None:
+    html = "<table class='data grid'><tr><td>1</td></tr><tr><td>2</td></tr></table>"
+    doc = Document(html)
+
+    assert doc.find("td").text == "1"
+    last_row = doc.xpath_first("//table[contains(@class,'data')]//tr[last()]")
+    assert last_row is not None
+    assert last_row.tag == "tr"
+    assert last_row.text == "2"
+
+    with pytest.raises(ValueError, match=r"//div\["):
+        doc.xpath("//div[")
+
+
 def test_version_exposed() -> None:
     assert __version__ == importlib.metadata.version("scraper-rust")
 
@@ -237,18 +2433,53 @@ def test_document_close_releases_resources(sample_html: str) -> None:
     doc.close()
     doc.close()  # idempotent
 
-    assert doc.html == ""
-    assert doc.text == ""
-    assert doc.select("a") == []
-    assert doc.select_first("a") is None
-    assert doc.find("a") is None
-    assert doc.xpath("//a") == []
-    assert doc.xpath_first("//a") is None
+    assert doc.closed
+    for read in (
+        lambda: doc.html,
+        lambda: doc.text,
+        lambda: doc.title,
+        lambda: doc.select("a"),
+        lambda: doc.select_first("a"),
+        lambda: doc.find("a"),
+        lambda: doc.xpath("//a"),
+        lambda: doc.xpath_first("//a"),
+        lambda: doc.to_html(),
+        lambda: copy.copy(doc),
+    ):
+        with pytest.raises(ValueError, match="Document is closed"):
+            read()
+
+
+def test_document_context_manager_propagates_errors(sample_html: str) -> None:
+    with pytest.raises(RuntimeError):
+        with Document(sample_html) as doc:
+            raise RuntimeError("boom")
+
+    assert doc.closed
+    with pytest.raises(ValueError, match="Document is closed"):
+        doc.select("a")
+
+
+def test_elements_outlive_closed_document(sample_html: str) -> None:
+    doc = Document(sample_html)
+    links = doc.select("a[href]")
+    item = doc.find(".item")
+
+    doc.close()
+
+    assert [link.attr("href") for link in links] == ["/a", "/b"]
+    assert links[1].text == "Second"
+    assert item.html == '<a href="/a">First</a>'
+    assert item.attrs == {"class": "item", "data-id": "1"}
 
 
 def test_document_context_manager_closes(sample_html: str) -> None:
     with Document(sample_html) as doc:
+        assert not doc.closed
         assert doc.find("a[href]") is not None
 
-    assert doc.html == ""
-    assert doc.select("a") == []
+    assert doc.closed
+    with pytest.raises(ValueError, match="Document is closed"):
+        doc.html
+    with pytest.raises(ValueError, match="Document is closed"):
+        doc.select("a")